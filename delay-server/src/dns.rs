@@ -0,0 +1,114 @@
+//! Stand-in DNS server for the resolvers in the other packages, UDP only.
+//! Knows a couple of names under `delay.test`, answers NXDOMAIN for the rest
+//! and, like everything else here, takes 100ms to do it.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::time::{Duration, sleep};
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const RCODE_FORMERR: u16 = 1;
+const RCODE_NXDOMAIN: u16 = 3;
+
+/// The names we answer for, both families unless the name says otherwise
+fn lookup(name: &str) -> Option<Vec<IpAddr>> {
+    let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    match name.to_ascii_lowercase().as_str() {
+        "delay.test" => Some(vec![v4, v6]),
+        "v4.delay.test" => Some(vec![v4]),
+        "v6.delay.test" => Some(vec![v6]),
+        _ => None,
+    }
+}
+
+pub async fn serve(socket: UdpSocket) {
+    let socket = Arc::new(socket);
+    let mut buf = [0u8; 512];
+    loop {
+        let (n, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                println!("dns recv failed: {}", e);
+                continue;
+            }
+        };
+        let Some(response) = answer(&buf[..n]) else {
+            println!("Dropping malformed DNS query from {}", from);
+            continue;
+        };
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(100)).await;
+            if let Err(e) = socket.send_to(&response, from).await {
+                println!("dns send to {} failed: {}", from, e);
+            }
+        });
+    }
+}
+
+/// The response to one query, None when it's too broken to even echo the id back
+fn answer(query: &[u8]) -> Option<Vec<u8>> {
+    let header = query.get(..12)?;
+    let mut response = header[..2].to_vec(); // the id
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let Some((name, end)) = question(query).filter(|_| questions == 1) else {
+        response.extend_from_slice(&(0x8180 | RCODE_FORMERR).to_be_bytes());
+        response.extend_from_slice(&[0; 8]);
+        return Some(response);
+    };
+    let qtype = u16::from_be_bytes([query[end - 4], query[end - 3]]);
+    let addrs = lookup(&name);
+    let answers: Vec<IpAddr> = addrs
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|addr| match addr {
+            IpAddr::V4(_) => qtype == TYPE_A,
+            IpAddr::V6(_) => qtype == TYPE_AAAA,
+        })
+        .collect();
+    println!("DNS query for {} (type {}): {:?}", name, qtype, answers);
+
+    // response, recursion desired and available
+    let rcode = if addrs.is_some() { 0 } else { RCODE_NXDOMAIN };
+    response.extend_from_slice(&(0x8180 | rcode).to_be_bytes());
+    response.extend_from_slice(&[0, 1]);
+    response.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    response.extend_from_slice(&[0, 0, 0, 0]);
+    response.extend_from_slice(&query[12..end]);
+    for addr in answers {
+        // the name is a pointer to the one in the question, at offset 12
+        response.extend_from_slice(&[0xc0, 12]);
+        let (rtype, data) = match addr {
+            IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+        };
+        response.extend_from_slice(&rtype.to_be_bytes());
+        response.extend_from_slice(&[0, 1]); // class IN
+        response.extend_from_slice(&60u32.to_be_bytes()); // ttl
+        response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        response.extend_from_slice(&data);
+    }
+    Some(response)
+}
+
+/// The question's name and where the question ends (after type and class)
+fn question(query: &[u8]) -> Option<(String, usize)> {
+    let mut labels = vec![];
+    let mut pos = 12;
+    loop {
+        let len = *query.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // no compression in questions, nothing came before them to point at
+        let label = query.get(pos..pos + len).filter(|_| len < 64)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += len;
+    }
+    query.get(pos..pos + 4)?;
+    Some((labels.join("."), pos + 4))
+}
@@ -1,11 +1,29 @@
 //! Delay Server
 //!
 //! Minimal Axum server: GET / sleeps 100ms then responds "hello".
+//! POST /upload reads the whole body, no delay, and responds with its size.
+//! Bodies over 512 MiB get a 413.
+//! Listens on 127.0.0.1:3000 and [::1]:3000.
+//! Also echoes UDP datagrams back to their sender after 100ms on 127.0.0.1:3001,
+//! and answers DNS queries for `delay.test` after 100ms on 127.0.0.1:3053.
+//! With `--unix PATH` the same app is served on a unix socket too
+//! (`--unix @NAME` for the abstract namespace).
 
-use axum::{Router, routing::get};
+mod dns;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, rejection::BytesRejection},
+    routing::{get, post},
+};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
 use std::net::SocketAddr;
+use std::os::linux::net::SocketAddrExt;
+use std::sync::Arc;
 use tokio::{
-    net::TcpListener,
+    net::{TcpListener, UdpSocket, UnixListener},
     time::{Duration, sleep},
 };
 
@@ -15,13 +33,127 @@ async fn root() -> &'static str {
     "hello"
 }
 
+/// Well above the 16 MiB `zero-copy` sends by default, axum's own default is 2 MB
+const MAX_UPLOAD: usize = 512 * 1024 * 1024;
+
+/// The rejection goes back as it is: 413 over `MAX_UPLOAD`, 400 when the body broke off
+async fn upload(body: Result<Bytes, BytesRejection>) -> Result<String, BytesRejection> {
+    match body {
+        Ok(bytes) => {
+            println!("Received upload of {} bytes", bytes.len());
+            Ok(format!("received {} bytes", bytes.len()))
+        }
+        Err(e) => {
+            println!("upload failed: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Each datagram is echoed from its own task, so a burst of them all come back after ~100ms
+async fn udp_echo(socket: UdpSocket) {
+    let socket = Arc::new(socket);
+    // the largest UDP payload, anything bigger can't be sent in the first place
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let (n, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                println!("udp recv failed: {}", e);
+                continue;
+            }
+        };
+        println!(
+            "Received {} byte datagram from {}, echoing in 100ms...",
+            n, from
+        );
+        let datagram = buf[..n].to_vec();
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(100)).await;
+            if let Err(e) = socket.send_to(&datagram, from).await {
+                println!("udp send to {} failed: {}", from, e);
+            }
+        });
+    }
+}
+
+/// `axum::serve` only takes a `TcpListener` in 0.7, so drive hyper by hand
+async fn serve_unix(listener: UnixListener, app: Router) {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                println!("unix accept failed: {}", e);
+                continue;
+            }
+        };
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let conn = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service);
+            if let Err(e) = conn.await {
+                println!("unix connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// A path, or `@NAME` for the abstract namespace (no file, gone when the server exits)
+fn bind_unix(path: &str) -> std::io::Result<UnixListener> {
+    let listener = match path.strip_prefix('@') {
+        Some(name) => {
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            std::os::unix::net::UnixListener::bind_addr(&addr)?
+        }
+        None => {
+            // a socket file left by a previous run would make bind fail with EADDRINUSE
+            let _ = std::fs::remove_file(path);
+            std::os::unix::net::UnixListener::bind(path)?
+        }
+    };
+    listener.set_nonblocking(true)?;
+    UnixListener::from_std(listener)
+}
+
 #[tokio::main]
 async fn main() {
-    let app = Router::new().route("/", get(root));
+    let app = Router::new().route("/", get(root)).route(
+        "/upload",
+        post(upload).layer(DefaultBodyLimit::max(MAX_UPLOAD)),
+    );
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     println!("listening on http://{}", addr);
-
     let listener = TcpListener::bind(addr).await.expect("bind failed");
+
+    // IPv6 is optional, hosts without it still serve IPv4
+    let addr_v6 = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 3000));
+    match TcpListener::bind(addr_v6).await {
+        Ok(listener_v6) => {
+            println!("listening on http://{}", addr_v6);
+            tokio::spawn(axum::serve(listener_v6, app.clone()).into_future());
+        }
+        Err(e) => println!("not listening on {}: {}", addr_v6, e),
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--unix") {
+        let path = args.get(pos + 1).expect("--unix needs a PATH");
+        let listener = bind_unix(path).expect("unix bind failed");
+        println!("listening on unix:{}", path);
+        tokio::spawn(serve_unix(listener, app.clone()));
+    }
+
+    let udp_addr = SocketAddr::from(([127, 0, 0, 1], 3001));
+    let udp_socket = UdpSocket::bind(udp_addr).await.expect("udp bind failed");
+    println!("echoing udp on {}", udp_addr);
+    tokio::spawn(udp_echo(udp_socket));
+
+    let dns_addr = SocketAddr::from(([127, 0, 0, 1], 3053));
+    let dns_socket = UdpSocket::bind(dns_addr).await.expect("dns bind failed");
+    println!("answering dns on {}", dns_addr);
+    tokio::spawn(dns::serve(dns_socket));
+
     axum::serve(listener, app).await.expect("server error");
 }
@@ -0,0 +1,35 @@
+use std::{
+    future::Future,
+    os::linux::net::SocketAddrExt,
+    os::unix::net::{SocketAddr, UnixStream},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// `path` is a socket file, or `@name` for the abstract namespace
+pub fn connect_async(path: &str) -> ConnectFuture {
+    ConnectFuture {
+        path: path.to_string(),
+    }
+}
+
+pub struct ConnectFuture {
+    path: String,
+}
+
+impl Future for ConnectFuture {
+    type Output = UnixStream;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // a unix connect never waits for a handshake, it succeeds or fails right away
+        let stream = match self.path.strip_prefix('@') {
+            Some(name) => {
+                let addr = SocketAddr::from_abstract_name(name).unwrap();
+                UnixStream::connect_addr(&addr).unwrap()
+            }
+            None => UnixStream::connect(&self.path).unwrap(),
+        };
+        stream.set_nonblocking(true).unwrap();
+        Poll::Ready(stream)
+    }
+}
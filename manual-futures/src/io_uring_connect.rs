@@ -0,0 +1,58 @@
+use crate::io_uring_executor;
+use raw_syscall::sys_libc::{self, SockAddr, SocketFd, Sqe};
+use std::{
+    future::Future,
+    net::{SocketAddr, TcpStream},
+    os::fd::OwnedFd,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub fn connect_async(address: &str) -> ConnectFuture {
+    ConnectFuture {
+        address: address.to_string(),
+        in_flight: None,
+    }
+}
+
+pub struct ConnectFuture {
+    address: String,
+    /// the socket and the address the kernel reads, boxed so they don't move
+    in_flight: Option<(u64, SocketFd, Box<SockAddr>)>,
+}
+
+impl Future for ConnectFuture {
+    type Output = TcpStream;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let Some((token, _, _)) = &this.in_flight else {
+            let addr: SocketAddr = this.address.parse().unwrap();
+            let addr = Box::new(SockAddr::from(addr));
+            // a plain blocking socket, the ring does the waiting
+            let socket = sys_libc::create_tcp_socket(addr.family())
+                .unwrap_or_else(|err| panic!("socket failed {}", err));
+            // SAFETY: both stay in `in_flight` until the completion, or go to `cancel`
+            let token = unsafe { io_uring_executor::submit(Sqe::connect(&socket, &addr), cx) };
+            this.in_flight = Some((token, socket, addr));
+            return Poll::Pending;
+        };
+        let res = match io_uring_executor::poll_op(*token, cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(res) => res,
+        };
+        let (_, socket, _) = this.in_flight.take().unwrap();
+        if res < 0 {
+            panic!("Connect failed {}", std::io::Error::from_raw_os_error(-res));
+        }
+        Poll::Ready(TcpStream::from(OwnedFd::from(socket)))
+    }
+}
+
+impl Drop for ConnectFuture {
+    fn drop(&mut self) {
+        if let Some((token, socket, addr)) = self.in_flight.take() {
+            io_uring_executor::cancel(token, Box::new((socket, addr)));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! An executor on top of io_uring: completion based, unlike every other one here.
+//!
+//! A readiness future tries the call itself and only asks the reactor to tell
+//! it when to try again, its buffer is on loan to the kernel for the length of
+//! one syscall. With io_uring the call happens somewhere in the kernel after
+//! `poll` returned, so whatever it reads from or writes into has to stay put
+//! until the completion comes back, however long that takes and whatever
+//! happens to the future in between:
+//!
+//! - A future can be dropped at any `.await`. If it owned a `&mut [u8]` into
+//!   someone else's buffer, the kernel would write into memory that is gone
+//!   by then. So the futures own their buffers (`Vec`s, heap allocated, they
+//!   don't move with the future either).
+//! - Dropping the future still frees them, so `Drop` hands the buffer to the
+//!   reactor, which keeps it until the operation is over and asks the kernel
+//!   to stop it early with `IORING_OP_ASYNC_CANCEL`. Cancelling is a request
+//!   too: the operation may well complete anyway, the result is then lost.
+//! - `mem::forget` on a future leaks its buffer instead, the kernel writes
+//!   into memory nobody frees. Not pretty, but safe.
+//!
+//! This is why completion based runtimes hand buffers back and forth
+//! (`read(buf) -> (result, buf)`) instead of borrowing them like `AsyncRead`.
+//!
+//! The ring itself is raw-syscall's `sys_libc::IoUring`.
+use raw_syscall::sys_libc::{IoUring, Sqe};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+/// `user_data` of the cancel requests themselves, their result is not interesting
+const CANCEL_TOKEN: u64 = u64::MAX;
+
+thread_local! {
+    static REACTOR: RefCell<Option<Reactor>> = const { RefCell::new(None) };
+}
+
+enum Slot {
+    /// in flight, a future is waiting for it
+    Waiting(Waker),
+    /// the result, until the future comes to pick it up
+    Done(i32),
+    /// in flight, its future was dropped: keeps the buffers alive until it completes
+    Orphaned(Box<dyn Any>),
+}
+
+struct Reactor {
+    ring: IoUring,
+    slots: HashMap<u64, Slot>,
+    next_token: u64,
+}
+
+impl Reactor {
+    /// Submits everything queued, waits for at least one completion and hands them out
+    fn turn(&mut self) {
+        self.ring.submit_and_wait(1).unwrap();
+        while let Some(completion) = self.ring.pop() {
+            if completion.token == CANCEL_TOKEN {
+                continue;
+            }
+            match self.slots.remove(&completion.token) {
+                Some(Slot::Waiting(waker)) => {
+                    self.slots
+                        .insert(completion.token, Slot::Done(completion.result));
+                    waker.wake();
+                }
+                Some(Slot::Orphaned(buffers)) => {
+                    println!(
+                        "  dropped operation {} completed with {}, freeing its buffers",
+                        completion.token, completion.result
+                    );
+                    drop(buffers);
+                }
+                Some(Slot::Done(_)) | None => unreachable!("two completions for one operation"),
+            }
+        }
+    }
+}
+
+impl Drop for Reactor {
+    /// Closing the ring cancels what is left, but the kernel finishes that in
+    /// the background: wait for the orphans before their buffers are freed
+    fn drop(&mut self) {
+        while self
+            .slots
+            .values()
+            .any(|slot| matches!(slot, Slot::Orphaned(_)))
+        {
+            self.turn();
+        }
+    }
+}
+
+fn with_reactor<T>(f: impl FnOnce(&mut Reactor) -> T) -> T {
+    REACTOR.with_borrow_mut(|reactor| {
+        f(reactor
+            .as_mut()
+            .expect("io_uring futures only run in io_uring_executor::block_on"))
+    })
+}
+
+/// Queues `sqe` and returns the token to poll it with, it is submitted on the
+/// next turn. `cx`'s waker is woken when it completes.
+///
+/// SAFETY: the fd and buffers in `sqe` have to live until `poll_op` returned
+/// Ready, or be handed over to `cancel`.
+pub unsafe fn submit(sqe: Sqe, cx: &Context<'_>) -> u64 {
+    with_reactor(|reactor| {
+        let token = reactor.next_token;
+        reactor.next_token += 1;
+        unsafe { reactor.ring.push(sqe.token(token)).unwrap() };
+        reactor
+            .slots
+            .insert(token, Slot::Waiting(cx.waker().clone()));
+        token
+    })
+}
+
+/// The operation's result once it completed, `-errno` on failure
+pub fn poll_op(token: u64, cx: &mut Context<'_>) -> Poll<i32> {
+    with_reactor(|reactor| match reactor.slots.remove(&token) {
+        Some(Slot::Done(res)) => Poll::Ready(res),
+        Some(Slot::Waiting(_)) => {
+            reactor
+                .slots
+                .insert(token, Slot::Waiting(cx.waker().clone()));
+            Poll::Pending
+        }
+        _ => panic!("operation {} polled after it completed", token),
+    })
+}
+
+/// For a future dropped with its operation in flight: `buffers` are whatever
+/// the operation points into, freed once the kernel is done with them.
+pub fn cancel(token: u64, buffers: Box<dyn Any>) {
+    // the thread local is gone when the reactor itself is dropped, its ring with it
+    let _ = REACTOR.try_with(|reactor| {
+        let mut reactor = reactor.borrow_mut();
+        let Some(reactor) = reactor.as_mut() else {
+            return;
+        };
+        match reactor.slots.remove(&token) {
+            // completed, nobody picked it up: nothing left to wait for
+            Some(Slot::Done(_)) | None => {}
+            Some(_) => {
+                println!("  cancelling operation {}", token);
+                reactor.slots.insert(token, Slot::Orphaned(buffers));
+                let cancel = Sqe::async_cancel(token).token(CANCEL_TOKEN);
+                // SAFETY: the only thing it points to is a token
+                unsafe { reactor.ring.push(cancel).unwrap() };
+            }
+        }
+    });
+}
+
+struct Flag(AtomicBool);
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+pub fn block_on<T>(mut f: impl Future<Output = T>) -> T {
+    REACTOR.with_borrow_mut(|reactor| {
+        *reactor = Some(Reactor {
+            ring: IoUring::new(32).expect("Failed to set up io_uring"),
+            slots: HashMap::new(),
+            next_token: 0,
+        })
+    });
+    let woken = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut f = unsafe { Pin::new_unchecked(&mut f) };
+
+    println!("Starting io_uring_executor::block_on");
+    let mut tries = 0;
+    let out = loop {
+        println!("  io_uring_executor::block_on try {}", tries);
+        match f.as_mut().poll(&mut cx) {
+            Poll::Ready(out) => break out,
+            // the wakers are called from `turn`, on this very thread
+            Poll::Pending => {
+                while !woken.0.swap(false, Ordering::SeqCst) {
+                    with_reactor(Reactor::turn);
+                }
+            }
+        }
+        tries += 1;
+    };
+    REACTOR.with_borrow_mut(|reactor| reactor.take());
+    out
+}
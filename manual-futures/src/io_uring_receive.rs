@@ -0,0 +1,63 @@
+use crate::io_uring_executor;
+use raw_syscall::sys_libc::Sqe;
+use std::{
+    future::Future,
+    net::TcpStream,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub fn receive_async<'a>(stream: &'a mut TcpStream) -> ReceiveFuture<'a> {
+    ReceiveFuture {
+        stream,
+        buf: vec![0; 1024],
+        response: Vec::new(),
+        in_flight: None,
+    }
+}
+
+pub struct ReceiveFuture<'a> {
+    stream: &'a mut TcpStream,
+    /// what the kernel receives into, owned by the future and not borrowed from the caller
+    buf: Vec<u8>,
+    response: Vec<u8>,
+    in_flight: Option<u64>,
+}
+
+impl<'a> Future for ReceiveFuture<'a> {
+    type Output = Vec<u8>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        let this = self.get_mut();
+        loop {
+            let Some(token) = this.in_flight else {
+                let sqe = Sqe::recv(&*this.stream, &mut this.buf);
+                // SAFETY: `buf` is only touched again after the completion, or goes to `cancel`
+                this.in_flight = Some(unsafe { io_uring_executor::submit(sqe, cx) });
+                return Poll::Pending;
+            };
+            match io_uring_executor::poll_op(token, cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(res) if res < 0 => {
+                    panic!("Read failed {}", std::io::Error::from_raw_os_error(-res))
+                }
+                Poll::Ready(0) => {
+                    this.in_flight = None;
+                    return Poll::Ready(std::mem::take(&mut this.response));
+                }
+                Poll::Ready(n) => {
+                    this.response.extend_from_slice(&this.buf[..n as usize]);
+                    this.in_flight = None;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ReceiveFuture<'_> {
+    fn drop(&mut self) {
+        if let Some(token) = self.in_flight.take() {
+            io_uring_executor::cancel(token, Box::new(std::mem::take(&mut self.buf)));
+        }
+    }
+}
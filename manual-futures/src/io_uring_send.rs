@@ -0,0 +1,63 @@
+use crate::io_uring_executor;
+use raw_syscall::sys_libc::Sqe;
+use std::{
+    future::Future,
+    net::TcpStream,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub fn send_async<'a>(stream: &'a mut TcpStream, request: &str) -> SendFuture<'a> {
+    SendFuture {
+        stream,
+        // a copy we own, `request` could be gone before the kernel is done with it
+        request: request.as_bytes().to_vec(),
+        sent: 0,
+        in_flight: None,
+    }
+}
+
+pub struct SendFuture<'a> {
+    stream: &'a mut TcpStream,
+    request: Vec<u8>,
+    sent: usize,
+    in_flight: Option<u64>,
+}
+
+impl<'a> Future for SendFuture<'a> {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let this = self.get_mut();
+        loop {
+            let Some(token) = this.in_flight else {
+                if this.sent >= this.request.len() {
+                    return Poll::Ready(this.sent);
+                }
+                let sqe = Sqe::send(&*this.stream, &this.request[this.sent..]);
+                // SAFETY: `request` is only touched again after the completion, or goes to `cancel`
+                this.in_flight = Some(unsafe { io_uring_executor::submit(sqe, cx) });
+                return Poll::Pending;
+            };
+            match io_uring_executor::poll_op(token, cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(res) if res < 0 => {
+                    panic!("Write failed {}", std::io::Error::from_raw_os_error(-res))
+                }
+                // a partial send queues the rest
+                Poll::Ready(n) => {
+                    this.sent += n as usize;
+                    this.in_flight = None;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SendFuture<'_> {
+    fn drop(&mut self) {
+        if let Some(token) = self.in_flight.take() {
+            io_uring_executor::cancel(token, Box::new(std::mem::take(&mut self.request)));
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use raw_syscall::shutdown::Shutdown;
+use std::net::SocketAddr;
 use std::thread;
 
 use tokio::io::AsyncReadExt;
@@ -6,23 +8,38 @@ use tokio::net::TcpStream;
 
 mod connect;
 mod connect_mio;
+mod connect_unix;
 mod epoll_executor;
 mod executor_naive;
+mod io_uring_connect;
+mod io_uring_executor;
+mod io_uring_receive;
+mod io_uring_send;
 mod receive;
 mod receive_mio;
+mod receive_unix;
 mod send;
 mod send_mio;
+mod send_unix;
 mod waker;
 mod waker_connect;
 mod waker_executor;
 mod waker_reactor;
 mod waker_receive;
+mod waker_resolve;
 mod waker_send;
 
 const CYAN: &str = "\x1b[1;36m"; //bold cyan
 const PURPLE: &str = "\x1b[1;35m"; //bold purple
 const RESET: &str = "\x1b[0m";
 
+/// The delay server's stand-in DNS server and a name it knows
+const DNS_SERVER: &str = "127.0.0.1:3053";
+const DNS_HOST: &str = "delay.test";
+
+/// Where `cargo run -p delay-server -- --unix /tmp/delay-server.sock` listens
+const UNIX_SOCKET_PATH: &str = "/tmp/delay-server.sock";
+
 const REQUEST: &str = "GET / HTTP/1.1
 Host: localhost
 Connection: close
@@ -41,6 +58,9 @@ manual-futures explores making very simple async executors and reactors from scr
     - {PURPLE}epoll-executor{RESET}: Uses epoll to wait for readiness before polling again.
     - {PURPLE}futures-executor{RESET}: Uses the futures crate executor to run our own futures as proof they work.
     - {PURPLE}waker-executor{RESET}: Uses a custom waker and reactor to drive the futures to completion.
+    - {PURPLE}dns-executor [HOST] [NAMESERVER]{RESET}: waker-executor that resolves HOST first, defaults to delay.test at the delay server's 127.0.0.1:3053.
+    - {PURPLE}io-uring-executor{RESET}: Completion based: futures queue their calls on an io_uring and own their buffers.
+    - {PURPLE}unix-executor [PATH]{RESET}: Same futures as naive-executor over a unix socket (@NAME for abstract).
      "#,
     );
     println!("{}", msg);
@@ -57,11 +77,36 @@ fn main() {
     match command.as_str() {
         "naive-executor" => executor_naive::block_on(async_main()),
         "epoll-executor" => epoll_executor::block_on(async_main_mio()),
+        "io-uring-executor" => io_uring_executor::block_on(async_main_io_uring()),
+        "unix-executor" => {
+            let path = args.get(2).map_or(UNIX_SOCKET_PATH, |path| path.as_str());
+            executor_naive::block_on(async_main_unix(path))
+        }
         "futures-executor" => futures::executor::block_on(async_main()),
         "waker-executor" => {
-            thread::spawn(waker_reactor::run_reactor);
+            // blocked here, before the reactor thread exists, so no thread gets them.
+            // `shutdown` stays on this thread, its drop unblocks them here again.
+            let shutdown = Shutdown::new().unwrap();
+            let signals = shutdown.watcher().unwrap();
+            thread::spawn(move || waker_reactor::run_reactor(signals));
             thread::sleep(std::time::Duration::from_millis(100)); // Give the reactor some time to start
-            waker_executor::block_on(async_main_waker());
+            if waker_executor::block_on(async_main_waker()).is_none() {
+                println!("Stopped before the response came in");
+            }
+        }
+        "dns-executor" => {
+            let host = args.get(2).map_or(DNS_HOST, |host| host.as_str());
+            let nameserver = args.get(3).map_or(DNS_SERVER, |addr| addr.as_str());
+            let nameserver = nameserver
+                .parse()
+                .expect("NAMESERVER is an IP address and port");
+            let shutdown = Shutdown::new().unwrap();
+            let signals = shutdown.watcher().unwrap();
+            thread::spawn(move || waker_reactor::run_reactor(signals));
+            thread::sleep(std::time::Duration::from_millis(100));
+            if waker_executor::block_on(async_main_dns(host, nameserver)).is_none() {
+                println!("Stopped before the response came in");
+            }
         }
         "tokio-future" => {
             // Creates a Tokio runtime so that we initialize tokio's reactor
@@ -86,6 +131,17 @@ async fn async_main() {
     println!("Response:\n{}", response_str);
 }
 
+async fn async_main_unix(path: &str) {
+    let mut stream = connect_unix::connect_async(path).await;
+    println!("Connected to server (unix)");
+    let _ = send_unix::send_async(&mut stream, REQUEST).await;
+    println!("Request sent (unix)");
+    let response = receive_unix::receive_async(&mut stream).await;
+    println!("Response received (unix)");
+    let response_str = String::from_utf8_lossy(&response);
+    println!("Response:\n{}", response_str);
+}
+
 async fn async_main_mio() {
     let mut mio_stream = connect_mio::connect_async_mio("127.0.0.1:3000").await;
     println!("Connected to server (mio)");
@@ -108,6 +164,47 @@ async fn async_main_waker() {
     println!("Response:\n{}", response_str);
 }
 
+async fn async_main_dns(host: &str, nameserver: SocketAddr) {
+    let addrs = match waker_resolve::resolve_async(host, nameserver).await {
+        Ok(addrs) => addrs,
+        Err(err) => {
+            println!("Could not resolve {}: {}", host, err);
+            return;
+        }
+    };
+    println!("Resolved {} to {:?} (waker)", host, addrs);
+    let Some(&ip) = addrs.first() else {
+        println!("{} has no addresses", host);
+        return;
+    };
+    let addr = SocketAddr::new(ip, 3000).to_string();
+    let mut stream = waker_connect::connect_async(&addr).await;
+    println!("Connected to {} (waker)", addr);
+    let _ = waker_send::send_async(&mut stream, REQUEST).await;
+    println!("Request sent (waker)");
+    let response = waker_receive::receive_async(&mut stream).await;
+    println!("Response received (waker)");
+    let response_str = String::from_utf8_lossy(&response);
+    println!("Response:\n{}", response_str);
+}
+
+async fn async_main_io_uring() {
+    let mut stream = io_uring_connect::connect_async("127.0.0.1:3000").await;
+    println!("Connected to server (io_uring)");
+    let _ = io_uring_send::send_async(&mut stream, REQUEST).await;
+    println!("Request sent (io_uring)");
+    // a receive dropped with its recv in the ring, the server takes 100ms to answer:
+    // the reactor keeps its buffer and cancels it before the data shows up
+    let mut dropped = io_uring_receive::receive_async(&mut stream);
+    let _ = futures::poll!(&mut dropped);
+    drop(dropped);
+    println!("Dropped a receive in flight (io_uring)");
+    let response = io_uring_receive::receive_async(&mut stream).await;
+    println!("Response received (io_uring)");
+    let response_str = String::from_utf8_lossy(&response);
+    println!("Response:\n{}", response_str);
+}
+
 async fn tokio_async_main() {
     let mut stream = TcpStream::connect("127.0.0.1:3000").await.unwrap();
     println!("Connected to server (tokio-future)");
@@ -10,7 +10,7 @@ use crate::epoll_executor::REGISTRY;
 
 pub fn receive_async<'a>(stream: &'a mut TcpStream) -> ReceiveFuture<'a> {
     ReceiveFuture {
-        stream: stream,
+        stream,
         response: Vec::new(),
         state: ReceiveState::Receiving,
         registered: false,
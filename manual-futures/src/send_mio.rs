@@ -10,7 +10,7 @@ use crate::epoll_executor::REGISTRY;
 
 pub fn send_async<'a>(stream: &'a mut TcpStream, request: &str) -> SendFuture<'a> {
     SendFuture {
-        stream: stream,
+        stream,
         request: request.to_string(),
         sent: 0,
         state: SendState::Sending,
@@ -0,0 +1,60 @@
+use std::{
+    future::Future,
+    io::Write,
+    os::unix::net::UnixStream,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub fn send_async(stream: &mut UnixStream, request: &str) -> SendFuture {
+    SendFuture {
+        stream: stream.try_clone().unwrap(),
+        request: request.to_string(),
+        sent: 0,
+        state: SendState::Sending,
+    }
+}
+
+pub struct SendFuture {
+    stream: UnixStream,
+    request: String,
+    sent: usize,
+    state: SendState,
+}
+
+enum SendState {
+    Sending,
+    Done,
+}
+
+impl Future for SendFuture {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let this = self.get_mut();
+        match this.state {
+            SendState::Sending => {
+                let bytes = this.request.as_bytes();
+                match this.stream.write(bytes[this.sent..].as_ref()) {
+                    Ok(n) => {
+                        this.sent += n;
+                        if this.sent >= bytes.len() {
+                            this.state = SendState::Done;
+                            Poll::Ready(this.sent)
+                        } else {
+                            // still Sending, the rest goes out on the next poll
+                            cx.waker().wake_by_ref();
+                            Poll::Pending
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                    Err(e) => panic!("Write failed {}", e),
+                }
+            }
+            SendState::Done => Poll::Ready(this.sent),
+        }
+    }
+}
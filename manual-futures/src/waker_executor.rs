@@ -1,11 +1,13 @@
 use crate::waker::Parker;
+use crate::waker_reactor;
 use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll, Waker},
 };
 
-pub fn block_on<T>(mut f: impl Future<Output = T>) -> T {
+/// None if the reactor shut down first, the future is dropped unfinished
+pub fn block_on<T>(mut f: impl Future<Output = T>) -> Option<T> {
     let parker = Arc::new(Parker::new());
     let waker = Waker::from(parker.clone());
     let mut cx = Context::from_waker(&waker);
@@ -16,7 +18,9 @@ pub fn block_on<T>(mut f: impl Future<Output = T>) -> T {
     loop {
         println!("  waker_executor::block_on try {}", tries);
         match f.as_mut().poll(&mut cx) {
-            Poll::Ready(out) => break out,
+            Poll::Ready(out) => break Some(out),
+            // checked before parking too, the reactor may have stopped before we registered
+            Poll::Pending if waker_reactor::shutdown_requested() => break None,
             Poll::Pending => parker.park(),
         }
         tries += 1;
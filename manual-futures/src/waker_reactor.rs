@@ -1,11 +1,25 @@
+use mio::unix::SourceFd;
 use mio::{Events, Interest, Registry, Token};
+use raw_syscall::sys_libc::SignalFd;
 use std::{
-    sync::{Mutex, OnceLock},
+    os::fd::{AsFd, AsRawFd},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
     task::Waker,
 };
 
 pub static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
 static WAKER: OnceLock<Mutex<Option<Waker>>> = OnceLock::new();
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// the one future uses Token(0)
+const SIGNAL: Token = Token(1);
+
+/// Set by the reactor when it stops on SIGINT/SIGTERM, nothing will wake the executor anymore
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
 
 pub fn initialize_reactor() -> (mio::Poll, Events) {
     let epoll = mio::Poll::new().unwrap();
@@ -31,12 +45,46 @@ pub fn register(source: &mut impl mio::event::Source, interest: Interest, waker:
     }
 }
 
-pub fn run_reactor() {
+/// Takes `source` out of the reactor, the next `register` starts from scratch
+pub fn deregister(source: &mut impl mio::event::Source) {
+    *WAKER.get().unwrap().lock().unwrap() = None;
+    REGISTRY
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .deregister(source)
+        .unwrap();
+}
+
+/// `signals` is a `Shutdown::watcher`, the `Shutdown` made before spawning the
+/// reactor stays with the spawning thread. The reactor stops when it fires.
+pub fn run_reactor(signals: SignalFd) {
     let (mut epoll, mut events) = initialize_reactor();
+    epoll
+        .registry()
+        .register(
+            &mut SourceFd(&signals.as_fd().as_raw_fd()),
+            SIGNAL,
+            Interest::READABLE,
+        )
+        .unwrap();
 
     loop {
         epoll.poll(&mut events, None).unwrap();
 
+        if events.iter().any(|event| event.token() == SIGNAL)
+            && let Some(signal) = signals.read().unwrap()
+        {
+            println!("Reactor got signal {}, shutting down", signal);
+            SHUTDOWN.store(true, Ordering::SeqCst);
+            // the executor finds the flag once woken, and drops the future with its sockets
+            if let Some(waker) = WAKER.get().unwrap().lock().unwrap().as_ref() {
+                waker.wake_by_ref();
+            }
+            return;
+        }
+
         if !events.is_empty() {
             let waker_lock = WAKER.get().unwrap();
             if let Some(waker) = waker_lock.lock().unwrap().as_ref() {
@@ -0,0 +1,98 @@
+use mio::net::UdpSocket;
+use raw_syscall::sys_libc::dns_message::{self, TYPE_A, TYPE_AAAA};
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::waker_reactor;
+
+const READABLE: mio::Interest = mio::Interest::READABLE;
+
+/// A and AAAA for `host` from `nameserver`. There is no timer in the reactor,
+/// a lost datagram waits forever (Ctrl-C still stops it). An error rcode,
+/// NXDOMAIN included, is an error and not an empty list.
+pub fn resolve_async(host: &str, nameserver: SocketAddr) -> ResolveFuture {
+    ResolveFuture {
+        host: host.to_string(),
+        nameserver,
+        socket: None,
+        id: std::process::id() as u16,
+        answers: [None, None],
+    }
+}
+
+pub struct ResolveFuture {
+    host: String,
+    nameserver: SocketAddr,
+    socket: Option<UdpSocket>,
+    /// `id` for the A question, `id + 1` for AAAA
+    id: u16,
+    answers: [Option<Vec<IpAddr>>; 2],
+}
+
+impl Future for ResolveFuture {
+    type Output = Result<Vec<IpAddr>, anyhow::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let Some(socket) = this.socket.as_mut() else {
+            let local: SocketAddr = match this.nameserver {
+                SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+                SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+            };
+            let mut socket = UdpSocket::bind(local).unwrap();
+            // connected: the kernel drops datagrams from anyone but the nameserver
+            socket.connect(this.nameserver).unwrap();
+            for (id, qtype) in [(this.id, TYPE_A), (this.id.wrapping_add(1), TYPE_AAAA)] {
+                let query = dns_message::query(id, &this.host, qtype).unwrap();
+                socket.send(&query).unwrap();
+            }
+            waker_reactor::register(&mut socket, READABLE, cx.waker().clone());
+            this.socket = Some(socket);
+            return Poll::Pending;
+        };
+
+        let mut buf = [0; 512];
+        loop {
+            match socket.recv(&mut buf) {
+                Ok(n) => {
+                    let Ok(response) = dns_message::parse_response(&buf[..n]) else {
+                        println!("Ignoring a DNS response that doesn't parse");
+                        continue;
+                    };
+                    let ids = [this.id, this.id.wrapping_add(1)];
+                    let Some(i) = ids.iter().position(|&id| id == response.id) else {
+                        println!("Ignoring a stray DNS response");
+                        continue;
+                    };
+                    if response.rcode != 0 {
+                        waker_reactor::deregister(socket);
+                        return Poll::Ready(Err(anyhow::anyhow!(
+                            "{} answered {} with rcode {}",
+                            this.nameserver,
+                            this.host,
+                            response.rcode
+                        )));
+                    }
+                    this.answers[i] = Some(response.addrs);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => panic!("DNS receive failed {}", e),
+            }
+        }
+        match &this.answers {
+            [Some(v4), Some(v6)] => {
+                // frees the reactor's one slot for the connect that comes next
+                waker_reactor::deregister(socket);
+                Poll::Ready(Ok(v4.iter().chain(v6).copied().collect()))
+            }
+            _ => {
+                waker_reactor::register(socket, READABLE, cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Every client strategy against the same workload, side by side.
+//!
+//! Each strategy runs `--iterations` times with the `--count`, `--concurrency`
+//! and `--path` from the command line. The latencies are of single requests,
+//! from connect to the end of the response as the driver timed them, and the
+//! percentiles go over every request of every run. Throughput is over the
+//! whole runs. Wakeups and syscalls come from `stats`, which only sees our own
+//! wrappers: the std and mio strategies go around them, so they have none.
+use crate::config::{Config, Target};
+use crate::report::Report;
+use crate::shutdown::Interrupted;
+use crate::sys_libc::SockAddr;
+use crate::workload::Workload;
+use crate::{
+    non_blocking_epoll, non_blocking_mio, non_blocking_poll, non_blocking_select, non_blocking_std,
+    sequential, sequential_std,
+};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub type Driver = fn(SocketAddr, &Workload) -> Result<Report, anyhow::Error>;
+
+pub struct Strategy {
+    pub name: &'static str,
+    pub run: Driver,
+    /// Whether its syscalls go through `sys_libc` and show up in `stats`
+    pub counted: bool,
+}
+
+pub const STRATEGIES: [Strategy; 7] = [
+    Strategy {
+        name: "sequential",
+        run: sequential::sequential_calls,
+        counted: true,
+    },
+    Strategy {
+        name: "non_blocking_select",
+        run: non_blocking_select::non_blocking_calls,
+        counted: true,
+    },
+    Strategy {
+        name: "non_blocking_poll",
+        run: non_blocking_poll::non_blocking_calls,
+        counted: true,
+    },
+    Strategy {
+        name: "non_blocking_epoll",
+        run: |addr, workload| {
+            non_blocking_epoll::non_blocking_calls(Target::Addr(SockAddr::from(addr)), workload)
+        },
+        counted: true,
+    },
+    Strategy {
+        name: "sequential_std",
+        run: sequential_std::sequential_calls,
+        counted: false,
+    },
+    Strategy {
+        name: "non_blocking_std",
+        run: non_blocking_std::non_blocking_call,
+        counted: false,
+    },
+    Strategy {
+        name: "non_blocking_mio",
+        run: non_blocking_mio::non_blocking_calls,
+        counted: false,
+    },
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(anyhow::anyhow!(
+                "unknown format {}, expected table, json or csv",
+                s
+            )),
+        }
+    }
+}
+
+/// One strategy's runs, boiled down. The durations are request latencies.
+pub struct Summary {
+    pub name: &'static str,
+    pub min: Duration,
+    pub median: Duration,
+    /// With fewer than 100 requests in all the runs, the slowest one
+    pub p99: Duration,
+    /// Requests per second over all the runs
+    pub throughput: f64,
+    /// Per run, None when `stats` can't see the strategy
+    pub wakeups: Option<u64>,
+    pub syscalls: Option<u64>,
+}
+
+/// `config.iterations` runs of `strategy`, the first error stops it. So does
+/// Ctrl-C, and a run that is missing responses: its time would look too good.
+pub fn run_strategy(
+    strategy: &Strategy,
+    addr: SocketAddr,
+    config: &Config,
+) -> Result<Summary, anyhow::Error> {
+    let mut latencies = vec![];
+    let mut total = Duration::ZERO;
+    let (mut wakeups, mut syscalls) = (0, 0);
+    for _ in 0..config.iterations {
+        let report = (strategy.run)(addr, &config.workload)
+            .map_err(|err| err.context(format!("{} failed", strategy.name)))?;
+        if let Some(signal) = report.interrupted {
+            return Err(Interrupted(signal).into());
+        }
+        let complete = report.latencies.iter().flatten().count();
+        if complete != config.workload.count {
+            return Err(anyhow::anyhow!(
+                "{} got {} of {} responses, not timing it",
+                strategy.name,
+                complete,
+                config.workload.count
+            ));
+        }
+        latencies.extend(report.latencies.iter().flatten());
+        total += report.elapsed;
+        wakeups += report.wakeups;
+        syscalls += report.syscalls;
+    }
+
+    latencies.sort();
+    let per_run = |n: u64| strategy.counted.then(|| n / config.iterations as u64);
+    Ok(Summary {
+        name: strategy.name,
+        min: latencies[0],
+        median: percentile(&latencies, 50),
+        p99: percentile(&latencies, 99),
+        throughput: (config.workload.count * config.iterations) as f64 / total.as_secs_f64(),
+        wakeups: per_run(wakeups),
+        syscalls: per_run(syscalls),
+    })
+}
+
+/// The summaries in `config.format`
+pub fn report(summaries: &[Summary], config: &Config) -> String {
+    match config.format {
+        Format::Table => table(summaries),
+        Format::Json => json(summaries, config),
+        Format::Csv => csv(summaries),
+    }
+}
+
+/// Nearest rank: the shortest latency at least `p` percent of the requests got under
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn or_dash(n: Option<u64>) -> String {
+    n.map_or("-".to_string(), |n| n.to_string())
+}
+
+fn table(summaries: &[Summary]) -> String {
+    let mut out = format!(
+        "{:<20} {:>10} {:>10} {:>10} {:>10} {:>8} {:>9}\n",
+        "strategy", "min ms", "median ms", "p99 ms", "req/s", "wakeups", "syscalls"
+    );
+    for s in summaries {
+        let _ = writeln!(
+            out,
+            "{:<20} {:>10.2} {:>10.2} {:>10.2} {:>10.1} {:>8} {:>9}",
+            s.name,
+            ms(s.min),
+            ms(s.median),
+            ms(s.p99),
+            s.throughput,
+            or_dash(s.wakeups),
+            or_dash(s.syscalls)
+        );
+    }
+    out.push_str("latencies are per request, connect to the end of the response\n");
+    out.push_str("wakeups and syscalls are per run, - where they go around sys_libc\n");
+    out
+}
+
+/// By hand, the names are fixed and need no escaping
+fn json(summaries: &[Summary], config: &Config) -> String {
+    let null_or = |n: Option<u64>| n.map_or("null".to_string(), |n| n.to_string());
+    let strategies: Vec<String> = summaries
+        .iter()
+        .map(|s| {
+            format!(
+                "    {{\"strategy\": \"{}\", \"min_latency_ms\": {:.3}, \"median_latency_ms\": {:.3}, \"p99_latency_ms\": {:.3}, \"requests_per_sec\": {:.1}, \"wakeups\": {}, \"syscalls\": {}}}",
+                s.name,
+                ms(s.min),
+                ms(s.median),
+                ms(s.p99),
+                s.throughput,
+                null_or(s.wakeups),
+                null_or(s.syscalls)
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"iterations\": {},\n  \"count\": {},\n  \"concurrency\": {},\n  \"strategies\": [\n{}\n  ]\n}}\n",
+        config.iterations,
+        config.workload.count,
+        config.workload.concurrency,
+        strategies.join(",\n")
+    )
+}
+
+fn csv(summaries: &[Summary]) -> String {
+    let empty_or = |n: Option<u64>| n.map_or(String::new(), |n| n.to_string());
+    let mut out =
+        "strategy,min_latency_ms,median_latency_ms,p99_latency_ms,requests_per_sec,wakeups,syscalls\n"
+            .to_string();
+    for s in summaries {
+        let _ = writeln!(
+            out,
+            "{},{:.3},{:.3},{:.3},{:.1},{},{}",
+            s.name,
+            ms(s.min),
+            ms(s.median),
+            ms(s.p99),
+            s.throughput,
+            empty_or(s.wakeups),
+            empty_or(s.syscalls)
+        );
+    }
+    out
+}
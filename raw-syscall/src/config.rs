@@ -0,0 +1,170 @@
+//! The command line, parsed once in main and handed to every command.
+//! Options are `--name=value`, the address can also be the first argument
+//! that isn't an option. Commands ignore the options that mean nothing to them.
+//! The drivers only get the `Workload` part.
+use crate::bench::Format;
+use crate::cyan;
+use crate::non_blocking_epoll::Mode;
+use crate::sys_libc::{self, RESOLV_CONF_PATH, ResolvConf, SockAddr};
+use crate::workload::Workload;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The address argument. A hostname stays a name until a command needs the
+/// address, `non-blocking-epoll` looks it up in its own epoll loop.
+#[derive(Clone)]
+pub enum Target {
+    Addr(SockAddr),
+    Host { name: String, port: u16 },
+}
+
+pub struct Config {
+    /// None for the command's default, 127.0.0.1:3000 or :3001
+    pub target: Option<Target>,
+    pub workload: Workload,
+    pub restart_on_eintr: bool,
+    pub file: Option<PathBuf>,
+    /// Runs per strategy for bench
+    pub iterations: usize,
+    pub format: Format,
+    /// Where bench writes its results, stdout when None
+    pub output: Option<PathBuf>,
+}
+
+impl Config {
+    /// `args` without the program name and the command
+    pub fn parse(args: &[String]) -> Result<Self, anyhow::Error> {
+        let mut resolv_conf = ResolvConf::load(RESOLV_CONF_PATH)?;
+        if let Some(nameserver) = option(args, "nameserver")? {
+            resolv_conf.nameservers = vec![nameserver_addr(nameserver)?];
+        }
+        let addr_arg = match option(args, "addr")? {
+            Some(addr) => Some(addr),
+            None => args.iter().find(|arg| !arg.starts_with("--")).cloned(),
+        };
+        let (target, host) = match addr_arg {
+            Some(arg) => {
+                let (target, host) = parse_addr(&arg)?;
+                (Some(target), host)
+            }
+            None => (None, "localhost".to_string()),
+        };
+
+        let defaults = Workload::default();
+        let count = option(args, "count")?.unwrap_or(defaults.count);
+        let workload = Workload {
+            host,
+            count,
+            concurrency: option(args, "concurrency")?.unwrap_or(count),
+            path: option(args, "path")?.unwrap_or(defaults.path),
+            timeout: match option::<String>(args, "timeout")? {
+                Some(timeout) => parse_duration(&timeout)?,
+                None => defaults.timeout,
+            },
+            events_capacity: option(args, "events-capacity")?.unwrap_or(defaults.events_capacity),
+            mode: option::<Mode>(args, "mode")?.unwrap_or(defaults.mode),
+            resolv_conf,
+        };
+        let config = Config {
+            target,
+            workload,
+            restart_on_eintr: args.iter().any(|arg| arg == "--restart-on-eintr"),
+            file: option(args, "file")?,
+            iterations: option(args, "iterations")?.unwrap_or(10),
+            format: option(args, "format")?.unwrap_or(Format::Table),
+            output: option(args, "output")?,
+        };
+        let workload = &config.workload;
+        if workload.count == 0
+            || workload.concurrency == 0
+            || workload.events_capacity == 0
+            || config.iterations == 0
+        {
+            return Err(anyhow::anyhow!(
+                "--count, --concurrency, --events-capacity and --iterations need to be at least 1"
+            ));
+        }
+        if !workload.path.starts_with('/') {
+            return Err(anyhow::anyhow!("--path needs to start with /"));
+        }
+        Ok(config)
+    }
+
+    /// The address as an IP, `default` when none was given. A hostname is
+    /// looked up here and goes with its first address, blocking until it is known.
+    pub fn ip_addr(&self, default: SocketAddr) -> Result<SocketAddr, anyhow::Error> {
+        match &self.target {
+            None => Ok(default),
+            Some(Target::Addr(addr)) => addr
+                .to_std()
+                .ok_or_else(|| anyhow::anyhow!("This command needs an IP address, got {}", addr)),
+            Some(Target::Host { name, port }) => {
+                let addrs = sys_libc::resolve(name, *port, &self.workload.resolv_conf)?;
+                cyan!("{} is {:?}", name, addrs);
+                Ok(addrs[0])
+            }
+        }
+    }
+}
+
+/// The value of `--name=value`, None when it isn't there
+fn option<T: FromStr>(args: &[String], name: &str) -> Result<Option<T>, anyhow::Error>
+where
+    T::Err: std::fmt::Display,
+{
+    let prefix = format!("--{}=", name);
+    match args.iter().find_map(|arg| arg.strip_prefix(&prefix)) {
+        None => Ok(None),
+        Some(value) => match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => Err(anyhow::anyhow!("Invalid --{} {}: {}", name, value, err)),
+        },
+    }
+}
+
+/// `500ms`, `2s`, or a number of seconds
+fn parse_duration(arg: &str) -> Result<Duration, anyhow::Error> {
+    let invalid = || anyhow::anyhow!("Invalid --timeout {}, expected like 500ms or 2s", arg);
+    if let Some(ms) = arg.strip_suffix("ms") {
+        return Ok(Duration::from_millis(ms.parse().map_err(|_| invalid())?));
+    }
+    let secs: f64 = arg
+        .strip_suffix('s')
+        .unwrap_or(arg)
+        .parse()
+        .map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(secs).map_err(|_| invalid())
+}
+
+/// `unix:PATH` and `unix:@NAME` are unix sockets, anything else an IP address
+/// or a hostname and port, with the `Host` header to send
+fn parse_addr(arg: &str) -> Result<(Target, String), anyhow::Error> {
+    if let Some(name) = arg.strip_prefix("unix:") {
+        let addr = match name.strip_prefix('@') {
+            Some(name) => SockAddr::unix_abstract(name.as_bytes())?,
+            None => SockAddr::unix(name)?,
+        };
+        return Ok((Target::Addr(addr), "localhost".to_string()));
+    }
+    if let Ok(addr) = arg.parse::<SocketAddr>() {
+        return Ok((Target::Addr(SockAddr::from(addr)), "localhost".to_string()));
+    }
+    let (host, port) = arg
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow::anyhow!("{} is missing a port", arg))?;
+    let target = Target::Host {
+        name: host.to_string(),
+        port: port.parse()?,
+    };
+    Ok((target, host.to_string()))
+}
+
+/// An IP address, port 53 unless it has one
+fn nameserver_addr(arg: String) -> Result<SocketAddr, anyhow::Error> {
+    match arg.parse::<IpAddr>() {
+        Ok(ip) => Ok(SocketAddr::new(ip, 53)),
+        Err(_) => Ok(arg.parse()?),
+    }
+}
@@ -0,0 +1,71 @@
+//! Waking up a blocked `epoll_wait` from another thread with an eventfd.
+//!
+//! The loop waits with a long timeout and no sockets ready, a second thread
+//! notifies the eventfd and the wait returns early. This is what a waker does
+//! in an executor that owns its own epoll: the task is woken on another thread,
+//! the thread running the loop has to stop sleeping and poll it.
+use raw_syscall::sys_libc::{Epoll, EventFd, Events, Interest, stats};
+use raw_syscall::workload::Workload;
+use raw_syscall::{cyan, green};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EVENTFD_TOKEN: u64 = 0;
+
+pub fn eventfd_wakeup(workload: &Workload) -> Result<(), anyhow::Error> {
+    let start = Instant::now();
+    green!("--- eventfd_wakeup ---");
+    stats::reset();
+    let epoll = Epoll::new()?;
+    let mut events = Events::with_capacity(workload.events_capacity);
+    let eventfd = EventFd::new()?;
+    // level triggered: the eventfd stays readable until we drain it
+    epoll.add(&eventfd, EVENTFD_TOKEN, Interest::READABLE)?;
+
+    thread::scope(|scope| -> Result<(), anyhow::Error> {
+        let waker = scope.spawn(|| -> Result<(), anyhow::Error> {
+            thread::sleep(Duration::from_millis(200));
+            cyan!("Waker thread: one notify");
+            eventfd.notify()?;
+
+            thread::sleep(Duration::from_millis(200));
+            // three notifies before the loop runs are one wakeup, the counter says 3
+            cyan!("Waker thread: three notifies in a row");
+            for _ in 0..3 {
+                eventfd.notify()?;
+            }
+            Ok(())
+        });
+
+        // way longer than the demo takes by default, hitting it means the wakeup got lost
+        let timeout = workload.timeout;
+        let mut notifications = 0;
+        while notifications < 4 {
+            let waited = Instant::now();
+            if epoll.wait(&mut events, Some(timeout))? == 0 {
+                return Err(anyhow::anyhow!("Timeout, the waker never got through"));
+            }
+            for (token, readiness) in events.iter() {
+                if token == EVENTFD_TOKEN && readiness.is_readable() {
+                    let count = eventfd.drain()?;
+                    notifications += count;
+                    println!(
+                        "Woken up after {:?} of a {:?} timeout, counter was {}",
+                        waited.elapsed(),
+                        timeout,
+                        count
+                    );
+                }
+            }
+        }
+        waker.join().expect("waker thread panicked")
+    })?;
+
+    println!("Eventfd wakeup duration: {:?}", start.elapsed());
+    cyan!(
+        "{} wakeups, {} syscalls",
+        stats::wakeups(),
+        stats::syscalls()
+    );
+    Ok(())
+}
@@ -0,0 +1,229 @@
+//! The same requests as the epoll driver, completion based.
+//!
+//! With epoll the kernel says a socket is ready and we make the call. With
+//! io_uring we queue the call itself (connect, send, recv) and the kernel
+//! tells us when it is done and what it returned. The sockets are plain
+//! blocking ones: the kernel parks the operation, never us. Every trip into
+//! the kernel submits whatever the previous completions queued up and waits
+//! for the next ones. The overall timeout is an operation in the ring too.
+//!
+//! However a round ends, whatever is still in the ring is cancelled and its
+//! completion reaped before the memory it points at goes away. Closing the
+//! ring fd would cancel them too, but asynchronously: the kernel could still
+//! be reading or writing.
+use crate::report::{Report, TimedResponses};
+use crate::shutdown::{SIGNAL_TOKEN, Shutdown};
+use crate::sys_libc::libc::timespec;
+use crate::sys_libc::{self, Errno, IoUring, SockAddr, Sqe, SysError, TcpNoDelay, stats};
+use crate::workload::Workload;
+use crate::{cyan, green};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+const TIMEOUT_TOKEN: u64 = u64::MAX - 1;
+/// For the cancellations themselves, nothing waits on their completions
+const CANCEL_TOKEN: u64 = u64::MAX - 2;
+
+/// Everything an entry in the ring points at, boxed so none of it moves while
+/// the kernel has it. If the ring can't be emptied all of it is leaked together.
+struct Pinned {
+    timeout: timespec,
+    request: Vec<u8>,
+    server_addr: SockAddr,
+    buffers: Vec<[u8; 4096]>,
+}
+
+/// Which operation a socket has in the ring, at most one at a time
+enum State {
+    Connecting,
+    /// how much of the request is out already
+    Sending(usize),
+    Receiving,
+    Done,
+}
+
+pub fn io_uring_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
+    green!("--- io_uring_calls ---");
+    stats::reset();
+    let server_addr = SockAddr::from(addr);
+    let shutdown = Shutdown::new()?;
+    let request = workload.request();
+
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        let (responses, round_latencies) =
+            run_round(round.len(), &server_addr, &request, workload, &shutdown)?;
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
+    }
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
+}
+
+/// `count` requests at the same time, on a ring of their own. Their responses
+/// and latencies, counted from queueing the connects.
+fn run_round(
+    count: usize,
+    server_addr: &SockAddr,
+    request: &str,
+    workload: &Workload,
+    shutdown: &Shutdown,
+) -> Result<TimedResponses, anyhow::Error> {
+    let mut sockets = vec![];
+    for _ in 0..count {
+        let socket = sys_libc::create_tcp_socket(server_addr.family())?;
+        sys_libc::set_opt(&socket, TcpNoDelay, true)?; // disable Nagle's algorithm
+        sockets.push(socket);
+    }
+    let mut pinned = Box::new(Pinned {
+        timeout: timespec::from(workload.timeout),
+        request: request.as_bytes().to_vec(),
+        server_addr: *server_addr,
+        buffers: vec![[0u8; 4096]; sockets.len()],
+    });
+    let Pinned {
+        timeout,
+        request,
+        server_addr,
+        buffers,
+    } = &mut *pinned;
+    let mut responses = vec![Vec::new(); sockets.len()];
+    let mut latencies = vec![None; sockets.len()];
+    let mut states: Vec<State> = sockets.iter().map(|_| State::Connecting).collect();
+
+    // one entry per socket plus the timeout and the signal poll, so the first
+    // push of all of them needs no submit in between
+    let entries = (count + 2).next_power_of_two() as u32;
+    let mut ring = match IoUring::new(entries) {
+        Err(err) if matches!(err.kind, Errno::ENOSYS | Errno::EPERM) => {
+            return Err(anyhow::anyhow!("io_uring is not available here: {}", err));
+        }
+        ring => ring?,
+    };
+    // the tokens with an operation in the ring
+    let mut in_flight = HashSet::new();
+
+    let started = Instant::now();
+    let mut run = || -> Result<(), anyhow::Error> {
+        // SAFETY: everything the entries point to is in `pinned`, which stays
+        // put until `cancel_in_flight` has reaped them, however `run` returns
+        unsafe {
+            ring.push(Sqe::timeout(timeout, 0).token(TIMEOUT_TOKEN))?;
+            in_flight.insert(TIMEOUT_TOKEN);
+            ring.push(Sqe::poll_readable(shutdown).token(SIGNAL_TOKEN))?;
+            in_flight.insert(SIGNAL_TOKEN);
+            for (idx, socket) in sockets.iter().enumerate() {
+                ring.push(Sqe::connect(socket, server_addr).token(idx as u64))?;
+                in_flight.insert(idx as u64);
+            }
+        }
+
+        while states.iter().any(|s| !matches!(s, State::Done)) {
+            let submitted = ring.submit_and_wait(1)?;
+            cyan!("Submitted {} operations", submitted);
+            while let Some(completion) = ring.pop() {
+                in_flight.remove(&completion.token);
+                match completion.token {
+                    SIGNAL_TOKEN => {
+                        if shutdown.check()?.is_some() {
+                            let done = states.iter().filter(|s| matches!(s, State::Done)).count();
+                            cyan!(
+                                "Stopping with {} of {} responses complete",
+                                done,
+                                sockets.len()
+                            );
+                            return Ok(());
+                        }
+                        // SAFETY: see above
+                        unsafe { ring.push(Sqe::poll_readable(shutdown).token(SIGNAL_TOKEN))? };
+                        in_flight.insert(SIGNAL_TOKEN);
+                    }
+                    TIMEOUT_TOKEN => {
+                        return Err(anyhow::anyhow!("Timeout waiting for completions"));
+                    }
+                    token => {
+                        let idx = token as usize;
+                        let socket = &sockets[idx];
+                        cyan!("io_uring completion: {}, {}", socket, completion.result);
+                        let next = match states[idx] {
+                            State::Connecting => {
+                                completion.result("connect")?;
+                                State::Sending(0)
+                            }
+                            State::Sending(sent) => {
+                                let sent = sent + completion.result("send")? as usize;
+                                if sent < request.len() {
+                                    cyan!("Partial send of {} bytes", sent);
+                                    State::Sending(sent)
+                                } else {
+                                    State::Receiving
+                                }
+                            }
+                            State::Receiving => match completion.result("recv")? as usize {
+                                0 => {
+                                    latencies[idx] = Some(started.elapsed());
+                                    State::Done
+                                }
+                                n => {
+                                    responses[idx].extend_from_slice(&buffers[idx][..n]);
+                                    State::Receiving
+                                }
+                            },
+                            State::Done => State::Done,
+                        };
+                        // queue what comes next, it goes out with the next submit
+                        // SAFETY: see above
+                        unsafe {
+                            match next {
+                                State::Sending(sent) => {
+                                    ring.push(Sqe::send(socket, &request[sent..]).token(token))?
+                                }
+                                State::Receiving => {
+                                    ring.push(Sqe::recv(socket, &mut buffers[idx]).token(token))?
+                                }
+                                State::Connecting | State::Done => {}
+                            }
+                        }
+                        if matches!(next, State::Sending(_) | State::Receiving) {
+                            in_flight.insert(token);
+                        }
+                        states[idx] = next;
+                    }
+                }
+            }
+        }
+        Ok(())
+    };
+    let result = run();
+
+    if let Err(err) = cancel_in_flight(&mut ring, &mut in_flight) {
+        // the kernel may still use any of it, leaked is better than reused
+        cyan!("Could not cancel what is left in the ring: {}", err);
+        std::mem::forget(pinned);
+    }
+    result.map(|()| (responses, latencies))
+}
+
+/// Cancels every operation in `in_flight` and waits until each one completed
+fn cancel_in_flight(ring: &mut IoUring, in_flight: &mut HashSet<u64>) -> Result<(), SysError> {
+    if in_flight.is_empty() {
+        return Ok(());
+    }
+    cyan!("Cancelling {} operations", in_flight.len());
+    for &token in in_flight.iter() {
+        // SAFETY: a cancellation points at nothing
+        unsafe { ring.push(Sqe::async_cancel(token).token(CANCEL_TOKEN))? };
+    }
+    while !in_flight.is_empty() {
+        ring.submit_and_wait(1)?;
+        while let Some(completion) = ring.pop() {
+            in_flight.remove(&completion.token);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! TCP (and some UDP) from Rust on top of hand written libc calls.
+//!
+//! `sys_libc` is the wrappers: fd types that close on drop, `SysError` with
+//! the errno, would block as `Ok(None)`, and `stats` counting every syscall.
+//! The client drivers run a `Workload` with one strategy each, from blocking
+//! calls to select, poll, epoll, io_uring, std and mio, and hand back a
+//! `Report`. Nothing is printed unless `set_verbose(true)`, the CLI in
+//! `main.rs` turns it on and prints the reports.
+#![allow(bad_style)]
+pub mod bench;
+pub mod config;
+pub mod io_uring_calls;
+pub mod macros;
+pub mod non_blocking_epoll;
+pub mod non_blocking_mio;
+pub mod non_blocking_poll;
+pub mod non_blocking_select;
+pub mod non_blocking_std;
+pub mod non_blocking_udp_epoll;
+pub mod report;
+pub mod sequential;
+pub mod sequential_std;
+pub mod shutdown;
+pub mod sys_libc;
+// mirrors libc.rs, which has a few calls no wrapper uses yet
+#[cfg(feature = "raw-syscalls")]
+#[allow(dead_code)]
+mod sys_raw;
+pub mod timer_epoll;
+pub mod workload;
+
+pub use config::Config;
+pub use macros::set_verbose;
+pub use report::Report;
+pub use workload::Workload;
@@ -1,13 +1,32 @@
+//! Logging for the drivers and the wrappers, off unless someone asks for it:
+//! a library shouldn't talk on its own. The CLI turns it on.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Whether `cyan!` and `green!` print, process wide
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
 #[macro_export]
 macro_rules! cyan {
     ($($arg:tt)*) => ({
-        println!("\x1b[36m{}\x1b[0m", format!($($arg)*));
+        if $crate::macros::verbose() {
+            println!("\x1b[36m{}\x1b[0m", format!($($arg)*));
+        }
     })
 }
 
 #[macro_export]
 macro_rules! green {
     ($($arg:tt)*) => ({
-        println!("\x1b[32m{}\x1b[0m", format!($($arg)*));
+        if $crate::macros::verbose() {
+            println!("\x1b[32m{}\x1b[0m", format!($($arg)*));
+        }
     })
 }
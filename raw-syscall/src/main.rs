@@ -1,14 +1,17 @@
-#![allow(bad_style)]
-#![allow(unused)]
-mod macros;
-mod non_blocking_epoll;
-mod non_blocking_mio;
-mod non_blocking_poll;
-mod non_blocking_select;
-mod non_blocking_std;
-mod sequential;
-mod sequential_std;
-mod sys_libc;
+//! The command line on top of the library: parses the options, runs a driver
+//! and prints its report. The servers and the one-off demos only live here.
+mod eventfd_wakeup;
+mod non_blocking_server;
+mod scatter_gather;
+mod zero_copy;
+
+use non_blocking_server::Multiplexer;
+use raw_syscall::bench::{self, STRATEGIES};
+use raw_syscall::config::Target;
+use raw_syscall::shutdown::Interrupted;
+use raw_syscall::sys_libc::{self, SockAddr};
+use raw_syscall::*;
+use std::net::SocketAddr;
 
 const CYAN: &str = "\x1b[1;36m"; //bold cyan
 const PURPLE: &str = "\x1b[1;35m"; //bold purple
@@ -17,9 +20,14 @@ const RESET: &str = "\x1b[0m";
 fn help() {
     let msg = format!(
         r#"{CYAN}
-Usage: cargo run --bin raw-syscall -- <command>{RESET}
+Usage: cargo run --bin raw-syscall -- <command> [address] [options]{RESET}
 
 raw-syscall explores making TCP calls from Rust using linux libc calls directly.
+Requests go to {PURPLE}address{RESET}, defaults to 127.0.0.1:3000 (use [::1]:3000 for IPv6).
+Hostnames work too (localhost:3000), looked up in /etc/hosts and then with DNS, see --nameserver.
+non-blocking-epoll also takes unix:PATH or unix:@NAME (abstract namespace), see delay-server --unix.
+Servers listen on {PURPLE}address{RESET}, defaults to 127.0.0.1:3001.
+UDP commands send to {PURPLE}address{RESET}, defaults to the delay server's UDP echo on 127.0.0.1:3001.
 
 {CYAN}Commands:{RESET}
     - {PURPLE}seq-calls{RESET}: Make TCP calls sequentially using blocking syscalls.
@@ -29,6 +37,31 @@ raw-syscall explores making TCP calls from Rust using linux libc calls directly.
     - {PURPLE}std-seq-calls{RESET}: Make TCP calls sequentially using Rust std library.
     - {PURPLE}std-non-blocking-calls{RESET}: Make TCP calls using non-blocking sockets and std library.
     - {PURPLE}mio-non-blocking-calls{RESET}: Make TCP calls using non-blocking sockets and mio crate.
+    - {PURPLE}non-blocking-udp-epoll{RESET}: Send UDP datagrams using non-blocking sockets and epoll().
+    - {PURPLE}timer-epoll{RESET}: Make TCP calls with epoll(), a timerfd per request as its timeout and a periodic timerfd.
+    - {PURPLE}io-uring-calls{RESET}: Make TCP calls by queueing connect/send/recv on an io_uring and reaping completions.
+    - {PURPLE}eventfd-wakeup{RESET}: Wake up a blocked epoll_wait from another thread with an eventfd.
+    - {PURPLE}bench{RESET}: Run every client strategy from seq-calls to mio-non-blocking-calls --iterations times and compare them.
+    - {PURPLE}zero-copy{RESET}: Upload a file to the delay server with read/write, sendfile and splice, and compare throughput.
+    - {PURPLE}scatter-gather{RESET}: writev/readv, an fd passed with SCM_RIGHTS and SO_TIMESTAMP on UDP, no server needed.
+    - {PURPLE}server-select{RESET}: Serve TCP clients from a non-blocking accept loop using select().
+    - {PURPLE}server-poll{RESET}: Serve TCP clients from a non-blocking accept loop using poll().
+    - {PURPLE}server-epoll{RESET}: Serve TCP clients from a non-blocking accept loop using epoll().
+
+{CYAN}Options:{RESET}
+    - {PURPLE}--addr=ADDR{RESET}: Same as the address argument.
+    - {PURPLE}--count=N{RESET}: Requests to make, defaults to 3. For non-blocking-udp-epoll the sockets, 2 datagrams each.
+    - {PURPLE}--concurrency=N{RESET}: Connections open at once, the requests go in rounds of N. Defaults to all of them.
+    - {PURPLE}--path=PATH{RESET}: What to GET, defaults to /.
+    - {PURPLE}--timeout=DURATION{RESET}: How long to wait for events before giving up (500ms, 2s), defaults to 5s.
+    - {PURPLE}--events-capacity=N{RESET}: Events one epoll_wait can return, defaults to 10.
+    - {PURPLE}--restart-on-eintr{RESET}: Restart syscalls interrupted by signals instead of failing.
+    - {PURPLE}--mode=level|edge|oneshot{RESET}: How non-blocking-epoll registers its sockets, defaults to edge.
+    - {PURPLE}--nameserver=ADDR{RESET}: Ask this DNS server instead of the ones in /etc/resolv.conf (127.0.0.1:3053 is the delay server's).
+    - {PURPLE}--file=PATH{RESET}: The file zero-copy uploads, defaults to a 16 MiB temp file.
+    - {PURPLE}--iterations=N{RESET}: Runs per strategy for bench, defaults to 10.
+    - {PURPLE}--format=table|json|csv{RESET}: How bench reports, defaults to table.
+    - {PURPLE}--output=PATH{RESET}: Write the bench report to PATH instead of stdout.
     "#,
     );
     println!("{}", msg);
@@ -41,16 +74,112 @@ fn main() -> Result<(), anyhow::Error> {
         return Ok(());
     }
     let command = &args[1];
-    match command.as_str() {
-        "seq-calls" => sequential::sequential_calls()?,
-        "non-blocking-select" => non_blocking_select::non_blocking_calls()?,
-        "non-blocking-poll" => non_blocking_poll::non_blocking_calls()?,
-        "non-blocking-epoll" => non_blocking_epoll::non_blocking_calls()?,
-        "std-seq-calls" => sequential_std::sequential_calls()?,
-        "std-non-blocking-calls" => non_blocking_std::non_blocking_call()?,
-        "mio-non-blocking-calls" => non_blocking_mio::non_blocking_calls()?,
-        _ => help(),
+    // bench reports on its own, the drivers' play by play would bury it
+    set_verbose(command != "bench");
+    let config = Config::parse(&args[2..])?;
+    if config.restart_on_eintr {
+        sys_libc::set_restart_on_eintr(true);
+    }
+    // our servers listen on 3001 over TCP, the delay server echoes UDP on the same port
+    let server_addr = || config.ip_addr(SocketAddr::from(([127, 0, 0, 1], 3001)));
+    let addr = || config.ip_addr(SocketAddr::from(([127, 0, 0, 1], 3000)));
+    let client = || -> Result<Option<Report>, anyhow::Error> {
+        let report = match command.as_str() {
+            "seq-calls" => sequential::sequential_calls(addr()?, &config.workload)?,
+            "non-blocking-select" => {
+                non_blocking_select::non_blocking_calls(addr()?, &config.workload)?
+            }
+            "non-blocking-poll" => {
+                non_blocking_poll::non_blocking_calls(addr()?, &config.workload)?
+            }
+            // the one client that also takes a unix socket
+            "non-blocking-epoll" => {
+                let target = match &config.target {
+                    Some(target) => target.clone(),
+                    None => Target::Addr(SockAddr::from(addr()?)),
+                };
+                non_blocking_epoll::non_blocking_calls(target, &config.workload)?
+            }
+            "std-seq-calls" => sequential_std::sequential_calls(addr()?, &config.workload)?,
+            "std-non-blocking-calls" => {
+                non_blocking_std::non_blocking_call(addr()?, &config.workload)?
+            }
+            "mio-non-blocking-calls" => {
+                non_blocking_mio::non_blocking_calls(addr()?, &config.workload)?
+            }
+            "non-blocking-udp-epoll" => {
+                non_blocking_udp_epoll::non_blocking_calls(server_addr()?, &config.workload)?
+            }
+            "timer-epoll" => timer_epoll::timer_calls(addr()?, &config.workload)?,
+            "io-uring-calls" => io_uring_calls::io_uring_calls(addr()?, &config.workload)?,
+            _ => return Ok(None),
+        };
+        Ok(Some(report))
+    };
+    let run = || -> Result<(), anyhow::Error> {
+        if let Some(report) = client()? {
+            print_report(&report);
+            return Ok(());
+        }
+        match command.as_str() {
+            "eventfd-wakeup" => eventfd_wakeup::eventfd_wakeup(&config.workload)?,
+            "bench" => run_bench(addr()?, &config)?,
+            "zero-copy" => zero_copy::zero_copy(addr()?, config.file.clone())?,
+            "scatter-gather" => scatter_gather::scatter_gather()?,
+            "server-select" => non_blocking_server::run_server(
+                server_addr()?,
+                Multiplexer::Select,
+                &config.workload,
+            )?,
+            "server-poll" => non_blocking_server::run_server(
+                server_addr()?,
+                Multiplexer::Poll,
+                &config.workload,
+            )?,
+            "server-epoll" => non_blocking_server::run_server(
+                server_addr()?,
+                Multiplexer::Epoll,
+                &config.workload,
+            )?,
+            _ => help(),
+        }
+        Ok(())
+    };
+    match run() {
+        // Ctrl-C before a loop had anything to show, not an error
+        Err(err) if err.is::<Interrupted>() => {
+            println!("{}", err);
+            Ok(())
+        }
+        result => result,
     }
+}
 
+fn print_report(report: &Report) {
+    for (i, response) in report.responses.iter().enumerate() {
+        println!("Response {}:\n{}", i, String::from_utf8_lossy(response));
+    }
+    println!("Duration: {:?}", report.elapsed);
+    println!("{} wakeups, {} syscalls", report.wakeups, report.syscalls);
+}
+
+/// The progress goes to stderr, with json and csv stdout is only the report
+fn run_bench(addr: SocketAddr, config: &Config) -> Result<(), anyhow::Error> {
+    let mut summaries = vec![];
+    for strategy in &STRATEGIES {
+        eprintln!(
+            "{}: {} runs of {} requests, {} at a time",
+            strategy.name, config.iterations, config.workload.count, config.workload.concurrency
+        );
+        summaries.push(bench::run_strategy(strategy, addr, config)?);
+    }
+    let report = bench::report(&summaries, config);
+    match &config.output {
+        Some(path) => {
+            std::fs::write(path, report)?;
+            println!("Wrote {}", path.display());
+        }
+        None => print!("{}", report),
+    }
     Ok(())
 }
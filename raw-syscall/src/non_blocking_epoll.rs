@@ -1,81 +1,271 @@
-use crate::sys_libc::libc;
-use crate::sys_libc::libc::epoll_event;
-use crate::sys_libc::{self, EpollEvent, EpollFd, SocketFd};
+//! The epoll driver in the three ways a socket can be registered.
+//!
+//! - Edge: register once for in and out. An event only fires when something
+//!   changes, so every wakeup has to drain the socket until EAGAIN or the rest
+//!   of the data sits there unnoticed.
+//! - Level: an event fires for as long as the socket is ready. One read per
+//!   wakeup is fine, but we have to stop asking for WRITABLE once the request
+//!   is out, an idle connected socket is always writable.
+//! - OneShot: the registration is disabled after the first event and has to be
+//!   re-armed with `EPOLL_CTL_MOD`, one extra syscall per wakeup. Only one
+//!   thread can ever get an event for the socket, which is why runtimes with
+//!   several threads on one epoll like it.
+use crate::config::Target;
+use crate::report::{Report, TimedResponses};
+use crate::shutdown::{Interrupted, SIGNAL_TOKEN, Shutdown};
+use crate::sys_libc::{self, Epoll, Events, Interest, SockAddr, SocketFd, TcpNoDelay, stats};
+use crate::workload::Workload;
 use crate::{cyan, green};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Instant;
 
-pub fn non_blocking_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
-    green!("--- non_blocking_epoll ---");
-    let epoll_fd = sys_libc::epoll_create1(0)?;
+/// Token for the DNS query while a hostname is looked up, before any socket is registered
+const DNS_TOKEN: u64 = u64::MAX - 1;
 
-    let mut events: [epoll_event; 10] = unsafe { std::mem::zeroed() };
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Level,
+    Edge,
+    OneShot,
+}
+
+impl Mode {
+    /// What to ask for while the request is not sent yet
+    fn sending(self) -> Interest {
+        match self {
+            Mode::Level => Interest::WRITABLE,
+            // never changed afterwards, the response shows up as a new edge
+            Mode::Edge => Interest::READABLE | Interest::WRITABLE | Interest::EDGE,
+            Mode::OneShot => Interest::WRITABLE | Interest::ONESHOT,
+        }
+    }
+
+    /// What to ask for once the request is out
+    fn receiving(self) -> Interest {
+        match self {
+            Mode::Level => Interest::READABLE,
+            Mode::Edge => self.sending(),
+            Mode::OneShot => Interest::READABLE | Interest::ONESHOT,
+        }
+    }
+}
 
-    let mut sockets = vec![];
-    let IN_AND_OUT_EDGE_TRIGGER = (libc::EPOLLIN | libc::EPOLLOUT | libc::EPOLLET) as u32;
-    for _ in 0..3 {
-        let socket = sys_libc::create_non_blocking_tcp_socket()?;
+impl FromStr for Mode {
+    type Err = anyhow::Error;
 
-        let mut event = EpollEvent::new(&socket, IN_AND_OUT_EDGE_TRIGGER as u32);
-        sys_libc::epoll_ctl(&epoll_fd, libc::EPOLL_CTL_ADD, &socket, &mut event)?;
-        sockets.push(socket);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "level" => Ok(Mode::Level),
+            "edge" => Ok(Mode::Edge),
+            "oneshot" => Ok(Mode::OneShot),
+            _ => Err(anyhow::anyhow!(
+                "unknown epoll mode {}, expected level, edge or oneshot",
+                s
+            )),
+        }
     }
+}
 
-    let server_addr = sys_libc::create_ipv4_sockaddr("127.0.0.1", 3000)?;
-    for socket in &sockets {
-        sys_libc::connect(socket, &server_addr)?;
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Level => write!(f, "level"),
+            Mode::Edge => write!(f, "edge"),
+            Mode::OneShot => write!(f, "oneshot"),
+        }
     }
+}
+
+/// `target` can also be a unix socket, the loop is the same once connected.
+/// A hostname is resolved first, with the queries in the same epoll as the sockets.
+pub fn non_blocking_calls(target: Target, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
+    let mode = workload.mode;
+    green!("--- non_blocking_epoll ({} triggered) ---", mode);
+    stats::reset();
+    let epoll = Epoll::new()?;
+    let mut events = Events::with_capacity(workload.events_capacity);
+    let shutdown = Shutdown::new()?;
+    epoll.add(&shutdown, SIGNAL_TOKEN, Interest::READABLE)?;
+    let server_addr = match target {
+        Target::Addr(addr) => addr,
+        Target::Host { name, port } => {
+            let mut on_signal = |_| -> Result<(), anyhow::Error> {
+                match shutdown.check()? {
+                    Some(signal) => Err(Interrupted(signal).into()),
+                    None => Ok(()),
+                }
+            };
+            let addrs = sys_libc::resolve_on(
+                &name,
+                port,
+                &workload.resolv_conf,
+                &epoll,
+                &mut events,
+                DNS_TOKEN,
+                &mut on_signal,
+            )?;
+            cyan!("{} is {:?}", name, addrs);
+            SockAddr::from(addrs[0])
+        }
+    };
+    let request = workload.request();
 
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        let mut sockets = vec![];
+        for idx in 0..round.len() {
+            let socket = match server_addr {
+                SockAddr::Unix(..) => sys_libc::create_unix_stream_socket(sys_libc::SOCK_NONBLOCK)?,
+                _ => {
+                    let socket = sys_libc::create_non_blocking_tcp_socket(server_addr.family())?;
+                    sys_libc::set_opt(&socket, TcpNoDelay, true)?; // disable Nagle's algorithm
+                    socket
+                }
+            };
+            // the token is the socket's index in the round, events lead straight back to it
+            epoll.add(&socket, idx as u64, mode.sending())?;
+            sockets.push(socket);
+        }
+
+        // the latencies count from here
+        let started = Instant::now();
+        for socket in &sockets {
+            sys_libc::connect(socket, &server_addr)?;
+        }
 
-    send_all(&sockets, request, &epoll_fd, &mut events)?;
-    let responses = receive_all_non_blocking(&sockets, &epoll_fd, &mut events)?;
-    for (i, response) in responses.iter().enumerate() {
-        println!("Response {}:\n{}", i, String::from_utf8_lossy(response));
+        let (responses, round_latencies) = run(
+            &sockets,
+            &request,
+            &epoll,
+            &mut events,
+            workload,
+            &shutdown,
+            started,
+        )?;
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
     }
 
-    println!("Non-blocking epoll duration: {:?}", start.elapsed());
-    Ok(())
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
 }
 
-fn receive_all_non_blocking(
+/// Sends the request once a socket is writable, then reads until the server closes.
+/// No sending before the first wait: the connect is still in flight.
+/// The latencies are from `started` to the end of each response.
+fn run(
     sockets: &[SocketFd],
-    epoll_fd: &EpollFd,
-    events: &mut [epoll_event],
-) -> Result<Vec<Vec<u8>>, anyhow::Error> {
-    let timeout = 5000; // 5 seconds
-
+    request: &str,
+    epoll: &Epoll,
+    events: &mut Events,
+    workload: &Workload,
+    shutdown: &Shutdown,
+    started: Instant,
+) -> Result<TimedResponses, anyhow::Error> {
+    let mode = workload.mode;
     let mut responses = vec![Vec::new(); sockets.len()];
+    // how much of the request is out on each socket
+    let mut sent = vec![0; sockets.len()];
     let mut finished = vec![false; sockets.len()];
+    let mut latencies = vec![None; sockets.len()];
 
-    // try to receive from all of them first (since we use edge-triggered)
-    for (idx, socket) in sockets.iter().enumerate() {
-        finished[idx] = read_until_would_block(socket, &mut responses[idx])?;
-    }
     while !finished.iter().all(|&f| f) {
-        let nfds = sys_libc::epoll_wait(epoll_fd, events, timeout)?;
-        if nfds == 0 {
-            return Err(anyhow::anyhow!("Timeout waiting for data"));
+        if epoll.wait(events, Some(workload.timeout))? == 0 {
+            return Err(anyhow::anyhow!("Timeout waiting for events"));
         }
-        for i in 0..nfds as usize {
-            let event = &events[i];
-            let events = event.events;
-            let fd = unsafe { event.data.u64 as i32 };
-            println!("Epoll event: fd={}, events={:#b}", fd, events);
-            let idx = sockets.iter().position(|s| s.0 == fd).unwrap();
-            if event.events & (libc::EPOLLHUP | libc::EPOLLERR as u32) != 0 {
-                println!("Socket {} closed or error, draining socket", sockets[idx]);
-                finished[idx] = true;
-                let f = read_until_would_block(&sockets[idx], &mut responses[idx])?;
-                sys_libc::epoll_ctl_remove(epoll_fd, &sockets[idx])?;
+        for (token, readiness) in events.iter() {
+            if token == SIGNAL_TOKEN {
+                if shutdown.check()?.is_some() {
+                    let done = finished.iter().filter(|&&f| f).count();
+                    cyan!(
+                        "Stopping with {} of {} responses complete",
+                        done,
+                        sockets.len()
+                    );
+                    return Ok((responses, latencies));
+                }
+                continue;
+            }
+            let idx = token as usize;
+            let socket = &sockets[idx];
+            cyan!("Epoll event: {}, {}", socket, readiness);
+            if finished[idx] {
+                continue;
+            }
+
+            if sent[idx] < request.len() {
+                if readiness.is_error() || readiness.is_hup() {
+                    let error = sys_libc::get_socket_error(socket)?;
+                    return Err(anyhow::anyhow!("Connect failed on {}: {}", socket, error));
+                }
+                if readiness.is_writable() {
+                    sent[idx] = send_request(socket, request.as_bytes(), sent[idx])?;
+                }
+                // level and oneshot switch over to readable here, edge keeps its registration
+                if sent[idx] == request.len() && mode != Mode::Edge {
+                    epoll.modify(socket, token, mode.receiving())?;
+                } else if mode == Mode::OneShot {
+                    epoll.modify(socket, token, mode.sending())?;
+                }
+                continue;
             }
 
-            if (event.events & libc::EPOLLIN as u32) != 0 && !finished[idx] {
-                finished[idx] = read_until_would_block(&sockets[idx], &mut responses[idx])?;
+            if readiness.is_readable() || readiness.is_hup() || readiness.is_error() {
+                finished[idx] = match mode {
+                    // the only time we hear about this data, take all of it
+                    Mode::Edge => read_until_would_block(socket, &mut responses[idx])?,
+                    // whatever is left wakes us up again
+                    Mode::Level | Mode::OneShot => read_once(socket, &mut responses[idx])?,
+                };
             }
+            if finished[idx] {
+                latencies[idx] = Some(started.elapsed());
+                epoll.delete(socket)?;
+            } else if mode == Mode::OneShot {
+                epoll.modify(socket, token, mode.receiving())?;
+            }
+        }
+    }
+
+    Ok((responses, latencies))
+}
+
+/// Sends the rest of `request` from `sent` on, until it is all out or the
+/// socket would block. How much is out by then.
+pub(crate) fn send_request(
+    socket: &SocketFd,
+    request: &[u8],
+    mut sent: usize,
+) -> Result<usize, anyhow::Error> {
+    while sent < request.len() {
+        match sys_libc::send(socket, &request[sent..])? {
+            Some(n) => {
+                sent += n;
+                if sent < request.len() {
+                    cyan!("Partial send, {} of {} bytes out", sent, request.len());
+                }
+            }
+            None => break, // would block, the rest goes on the next writable event
         }
     }
+    Ok(sent)
+}
 
-    Ok(responses)
+fn read_once(socket: &SocketFd, buffer: &mut Vec<u8>) -> Result<bool, anyhow::Error> {
+    let mut temp_buf = [0u8; 4096];
+    match sys_libc::recv(socket, &mut temp_buf)? {
+        None => Ok(false),
+        Some(0) => Ok(true),
+        Some(n) => {
+            buffer.extend_from_slice(&temp_buf[..n]);
+            Ok(false)
+        }
+    }
 }
 
 fn read_until_would_block(socket: &SocketFd, buffer: &mut Vec<u8>) -> Result<bool, anyhow::Error> {
@@ -96,56 +286,3 @@ fn read_until_would_block(socket: &SocketFd, buffer: &mut Vec<u8>) -> Result<boo
     };
     Ok(finished)
 }
-
-fn send_all(
-    sockets: &[SocketFd],
-    request: &str,
-    epoll_fd: &EpollFd,
-    events: &mut [epoll_event],
-) -> Result<(), anyhow::Error> {
-    let mut sent_requests = vec![false; sockets.len()];
-    for (idx, socket) in sockets.iter().enumerate() {
-        let ret = sys_libc::send(socket, request.as_bytes())?;
-        match ret {
-            Some(n) if n == request.len() => {
-                sent_requests[idx] = true;
-            }
-            Some(n) => {
-                sent_requests[idx] = true; // for simplicity, mark as sent
-                cyan!("Partial send of {} bytes", n);
-            }
-            None => {
-                // would block, try again later
-            }
-        }
-    }
-
-    while !sent_requests.iter().all(|&s| s) {
-        let nfds = sys_libc::epoll_wait(&epoll_fd, events, 5000)?;
-        if nfds == 0 {
-            return Err(anyhow::anyhow!("Timeout waiting for events"));
-        }
-        for i in 0..nfds as usize {
-            let event = &events[i];
-            let fd = unsafe { event.data.u64 as i32 };
-            let idx = sockets.iter().position(|s| s.0 == fd).unwrap();
-            if (event.events & libc::EPOLLOUT as u32) != 0 && !sent_requests[idx] {
-                let ret = sys_libc::send(&sockets[idx], request.as_bytes())?;
-                match ret {
-                    Some(n) if n == request.len() => {
-                        sent_requests[idx] = true;
-                    }
-                    Some(n) => {
-                        sent_requests[idx] = true; // for simplicity, mark as sent
-                        cyan!("Partial send of {} bytes", n);
-                    }
-                    None => {
-                        // would block, try again later
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
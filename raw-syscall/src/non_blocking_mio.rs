@@ -1,50 +1,91 @@
 use mio::Events;
 use mio::Poll;
-use mio::event::Source;
 use mio::net::TcpStream;
+use mio::unix::SourceFd;
 use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
-use crate::green;
+use crate::report::{Report, TimedResponses};
+use crate::shutdown::{Interrupted, Shutdown};
+use crate::sys_libc::stats;
+use crate::workload::Workload;
+use crate::{cyan, green};
+use std::os::fd::{AsFd, AsRawFd};
 
-pub fn non_blocking_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
+/// mio keeps `Token(usize::MAX)` for itself
+const SIGNAL: mio::Token = mio::Token(usize::MAX - 1);
+
+pub fn non_blocking_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
     green!("--- non_blocking_mio ---");
 
+    stats::reset();
     let mut poll = Poll::new()?;
-    let mut events = Events::with_capacity(10);
-
-    let mut streams = vec![];
-    for i in 0..3 {
-        let stream = create_non_blocking_stream("127.0.0.1:3000")?;
-        streams.push(stream);
-        poll.registry().register(
-            &mut streams[i],
-            mio::Token(i),
-            mio::Interest::READABLE | mio::Interest::WRITABLE,
-        )?;
-    }
+    let mut events = Events::with_capacity(workload.events_capacity);
+    let shutdown = Shutdown::new()?;
+    let signal_fd = shutdown.as_fd().as_raw_fd();
+    poll.registry()
+        .register(&mut SourceFd(&signal_fd), SIGNAL, mio::Interest::READABLE)?;
+    let request = workload.request();
+
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        // the latencies count from here
+        let started = Instant::now();
+        let mut streams = vec![];
+        for i in 0..round.len() {
+            let stream = create_non_blocking_stream(addr)?;
+            streams.push(stream);
+            poll.registry().register(
+                &mut streams[i],
+                mio::Token(i),
+                mio::Interest::READABLE | mio::Interest::WRITABLE,
+            )?;
+        }
 
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-    send_all(&mut streams, request, &mut poll, &mut events)?;
-    let responses = read_all_non_blocking(&mut streams, &mut poll, &mut events)?;
+        let timeout = workload.timeout;
+        send_all(
+            &mut streams,
+            &request,
+            &mut poll,
+            &mut events,
+            &shutdown,
+            timeout,
+        )?;
+        let (responses, round_latencies) = read_all_non_blocking(
+            &mut streams,
+            &mut poll,
+            &mut events,
+            &shutdown,
+            timeout,
+            started,
+        )?;
 
-    println!("Received all responses:");
-    for (i, response) in responses.iter().enumerate() {
-        println!("Response {}:\n{}", i, String::from_utf8_lossy(response));
+        cyan!("Received all responses");
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
     }
 
-    println!("Non-blocking mio duration: {:?}", start.elapsed());
-    Ok(())
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
 }
 
+/// The latencies are from `started` to the end of each response
 fn read_all_non_blocking(
     streams: &mut [TcpStream],
     poll: &mut Poll,
     events: &mut Events,
-) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    shutdown: &Shutdown,
+    timeout: Duration,
+    started: Instant,
+) -> Result<TimedResponses, anyhow::Error> {
     let mut responses = vec![Vec::new(); streams.len()];
     let mut done = vec![false; streams.len()];
-    let timeout = std::time::Duration::from_secs(5);
+    let mut latencies = vec![None; streams.len()];
 
     // try to read immediately
     for (i, stream) in streams.iter_mut().enumerate() {
@@ -57,6 +98,18 @@ fn read_all_non_blocking(
             return Err(anyhow::anyhow!("Timeout waiting for responses"));
         }
         for event in events.iter() {
+            if event.token() == SIGNAL {
+                if shutdown.check()?.is_some() {
+                    let finished = done.iter().filter(|&&d| d).count();
+                    cyan!(
+                        "Stopping with {} of {} responses complete",
+                        finished,
+                        streams.len()
+                    );
+                    return Ok((responses, latencies));
+                }
+                continue;
+            }
             let i = event.token().0;
             if done[i] {
                 continue;
@@ -73,9 +126,12 @@ fn read_all_non_blocking(
                 let _ = read_until_would_block(&mut streams[i], &mut responses[i]);
                 poll.registry().deregister(&mut streams[i])?;
             }
+            if done[i] {
+                latencies[i] = Some(started.elapsed());
+            }
         }
     }
-    Ok(responses)
+    Ok((responses, latencies))
 }
 
 fn read_until_would_block(
@@ -108,9 +164,10 @@ fn send_all(
     request: &str,
     poll: &mut Poll,
     events: &mut Events,
+    shutdown: &Shutdown,
+    timeout: Duration,
 ) -> Result<(), anyhow::Error> {
     let mut sent = vec![false; streams.len()];
-    let timeout = std::time::Duration::from_secs(5);
 
     // try to send immediately
     for (i, stream) in streams.iter_mut().enumerate() {
@@ -118,7 +175,7 @@ fn send_all(
             Ok(n) if n == request.len() => {
                 sent[i] = true;
             }
-            Ok(n) => {
+            Ok(_) => {
                 // partially sent, will try later
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -131,13 +188,19 @@ fn send_all(
     while !sent.iter().all(|&s| s) {
         poll.poll(events, Some(timeout))?;
         for event in events.iter() {
+            if event.token() == SIGNAL {
+                if let Some(signal) = shutdown.check()? {
+                    return Err(Interrupted(signal).into());
+                }
+                continue;
+            }
             let i = event.token().0;
             if !sent[i] && event.is_writable() {
                 match streams[i].write(request.as_bytes()) {
                     Ok(n) if n == request.len() => {
                         sent[i] = true;
                     }
-                    Ok(n) => {
+                    Ok(_) => {
                         // partially sent, will try later
                     }
                     Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -152,8 +215,7 @@ fn send_all(
     Ok(())
 }
 
-pub fn create_non_blocking_stream(addr: &str) -> Result<TcpStream, anyhow::Error> {
-    let addr = addr.parse().unwrap();
+pub fn create_non_blocking_stream(addr: SocketAddr) -> Result<TcpStream, anyhow::Error> {
     let stream = TcpStream::connect(addr)?;
     stream.set_nodelay(true)?; // disable Nagle's algorithm
     Ok(stream)
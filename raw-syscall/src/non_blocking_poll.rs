@@ -1,55 +1,142 @@
+use crate::non_blocking_epoll::send_request;
+use crate::report::{Report, TimedResponses};
+use crate::shutdown::{Interrupted, Shutdown};
 use crate::sys_libc::libc::{POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT};
-use crate::sys_libc::{self, PollFd, SocketFd};
+use crate::sys_libc::{self, PollFd, SockAddr, SocketFd, TcpNoDelay, stats};
+use crate::workload::Workload;
 use crate::{cyan, green};
+use std::net::SocketAddr;
+use std::time::Instant;
 
-pub fn non_blocking_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
+pub fn non_blocking_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
     green!("--- non_blocking_poll ---");
+    let server_addr = SockAddr::from(addr);
+    stats::reset();
+    let shutdown = Shutdown::new()?;
+    let request = workload.request();
+
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        let (responses, round_latencies) =
+            run_round(round.len(), &server_addr, &request, workload, &shutdown)?;
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
+    }
+
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
+}
+
+/// `count` requests at the same time, their responses and latencies
+fn run_round(
+    count: usize,
+    server_addr: &SockAddr,
+    request: &str,
+    workload: &Workload,
+    shutdown: &Shutdown,
+) -> Result<TimedResponses, anyhow::Error> {
     let mut sockets = vec![];
-    for _ in 0..3 {
-        let socket = sys_libc::create_non_blocking_tcp_socket()?;
+    for _ in 0..count {
+        let socket = sys_libc::create_non_blocking_tcp_socket(server_addr.family())?;
+        sys_libc::set_opt(&socket, TcpNoDelay, true)?; // disable Nagle's algorithm
         sockets.push(socket);
     }
 
-    let server_addr = sys_libc::create_ipv4_sockaddr("127.0.0.1", 3000)?;
-
+    // the latencies count from here
+    let started = Instant::now();
     for socket in sockets.iter() {
-        sys_libc::connect(socket, &server_addr)?;
+        sys_libc::connect(socket, server_addr)?;
     }
     let mut poll_fds = sockets
         .iter()
         .map(|socket| PollFd::new(socket, POLLOUT))
         .collect::<Vec<_>>();
+    // the signalfd rides along as the last entry
+    poll_fds.push(PollFd::new(shutdown, POLLIN));
+    // past i32::MAX ms the cast would wrap, and a negative timeout is forever to poll
+    let timeout = i32::try_from(workload.timeout.as_millis()).unwrap_or(i32::MAX);
 
-    wait_for_connections(&mut poll_fds)?;
+    wait_for_connections(&mut poll_fds, shutdown, timeout)?;
 
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-    for socket in sockets.iter() {
-        sys_libc::send(socket, request.as_bytes())?;
-    }
+    send_all(
+        &mut poll_fds,
+        &sockets,
+        request.as_bytes(),
+        shutdown,
+        timeout,
+    )?;
 
     // receive data from all sockets using non-blocking poll
-    let responses = receive_all_non_blocking(&mut poll_fds, &sockets)?;
+    receive_all_non_blocking(&mut poll_fds, &sockets, shutdown, timeout, started)
+}
 
-    for (i, response) in responses.iter().enumerate() {
-        println!("Response {}:\n{}", i, String::from_utf8_lossy(response));
+/// `request` on every socket. What a socket couldn't take right away goes out
+/// once poll says it is writable again, from where it stopped.
+pub fn send_all(
+    poll_fds: &mut [PollFd],
+    sockets: &[SocketFd],
+    request: &[u8],
+    shutdown: &Shutdown,
+    timeout: i32,
+) -> Result<(), anyhow::Error> {
+    let signal_idx = sockets.len();
+    let mut sent = vec![0; sockets.len()];
+    for (i, socket) in sockets.iter().enumerate() {
+        sent[i] = send_request(socket, request, 0)?;
     }
 
-    println!("Non-blocking poll duration: {:?}", start.elapsed());
+    while sent.iter().any(|&n| n < request.len()) {
+        // only the sockets with something left wait for POLLOUT
+        for (i, pfd) in poll_fds[..signal_idx].iter_mut().enumerate() {
+            pfd.set_events(if sent[i] < request.len() { POLLOUT } else { 0 });
+        }
+        poll_fds.iter_mut().for_each(PollFd::reset_revents);
+
+        let poll_result = sys_libc::poll(poll_fds, timeout)?;
+        if poll_result == 0 {
+            return Err(anyhow::anyhow!("Timeout sending requests"));
+        }
+        let errors: Vec<_> = poll_fds
+            .iter()
+            .filter(|pfd| pfd.revents() & (POLLERR | POLLHUP | POLLNVAL) != 0)
+            .collect();
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Error on sockets: {:?}", errors));
+        }
+        if poll_fds[signal_idx].revents() & POLLIN != 0
+            && let Some(signal) = shutdown.check()?
+        {
+            return Err(Interrupted(signal).into());
+        }
+
+        for (i, pfd) in poll_fds[..signal_idx].iter().enumerate() {
+            if pfd.revents() & POLLOUT != 0 {
+                sent[i] = send_request(&sockets[i], request, sent[i])?;
+            }
+        }
+    }
     Ok(())
 }
 
+/// The latencies are from `started` to the end of each response
 pub fn receive_all_non_blocking(
     poll_fds: &mut [PollFd],
     sockets: &[SocketFd],
-) -> Result<Vec<Vec<u8>>, anyhow::Error> {
-    let timeout = 5000; // 5 seconds
+    shutdown: &Shutdown,
+    timeout: i32,
+    started: Instant,
+) -> Result<TimedResponses, anyhow::Error> {
     // reset revents before polling
     poll_fds.iter_mut().for_each(PollFd::reset_revents);
     poll_fds.iter_mut().for_each(|pfd| pfd.set_events(POLLIN));
 
-    let mut responses = vec![Vec::new(); poll_fds.len()];
-    let mut finished = vec![false; poll_fds.len()];
+    let mut responses = vec![Vec::new(); sockets.len()];
+    let mut finished = vec![false; sockets.len()];
+    let mut latencies = vec![None; sockets.len()];
 
     while !finished.iter().all(|&f| f) {
         let poll_result = sys_libc::poll(poll_fds, timeout)?;
@@ -64,8 +151,17 @@ pub fn receive_all_non_blocking(
         if !errors.is_empty() {
             return Err(anyhow::anyhow!("Error on sockets: {:?}", errors));
         }
+        if poll_fds[sockets.len()].revents() & POLLIN != 0 && shutdown.check()?.is_some() {
+            let done = finished.iter().filter(|&&f| f).count();
+            cyan!(
+                "Stopping with {} of {} responses complete",
+                done,
+                sockets.len()
+            );
+            break;
+        }
 
-        for (i, pfd) in poll_fds.iter_mut().enumerate() {
+        for (i, pfd) in poll_fds[..sockets.len()].iter_mut().enumerate() {
             if finished[i] {
                 continue;
             }
@@ -75,6 +171,7 @@ pub fn receive_all_non_blocking(
                 match sys_libc::recv(socket, &mut buf)? {
                     Some(0) => {
                         finished[i] = true; // connection closed
+                        latencies[i] = Some(started.elapsed());
                     }
                     Some(n) => {
                         responses[i].extend_from_slice(&buf[..n]);
@@ -87,15 +184,22 @@ pub fn receive_all_non_blocking(
         poll_fds.iter_mut().for_each(PollFd::reset_revents);
     }
 
-    Ok(responses)
+    Ok((responses, latencies))
 }
 
-pub fn wait_for_connections(poll_fds: &mut [PollFd]) -> Result<(), anyhow::Error> {
-    let timeout = 5000; // 5 seconds
+pub fn wait_for_connections(
+    poll_fds: &mut [PollFd],
+    shutdown: &Shutdown,
+    timeout: i32,
+) -> Result<(), anyhow::Error> {
     // reset revents before polling
     poll_fds.iter_mut().for_each(PollFd::reset_revents);
+    let signal_idx = poll_fds.len() - 1;
 
-    while !poll_fds.iter().all(|pfd| pfd.revents() & POLLOUT != 0) {
+    while !poll_fds[..signal_idx]
+        .iter()
+        .all(|pfd| pfd.revents() & POLLOUT != 0)
+    {
         let poll_result = sys_libc::poll(poll_fds, timeout)?;
         if poll_result == 0 {
             return Err(anyhow::anyhow!("Timeout waiting for connections"));
@@ -108,6 +212,11 @@ pub fn wait_for_connections(poll_fds: &mut [PollFd]) -> Result<(), anyhow::Error
         if !errors.is_empty() {
             return Err(anyhow::anyhow!("Error on sockets: {:?}", errors));
         }
+        if poll_fds[signal_idx].revents() & POLLIN != 0
+            && let Some(signal) = shutdown.check()?
+        {
+            return Err(Interrupted(signal).into());
+        }
     }
 
     Ok(())
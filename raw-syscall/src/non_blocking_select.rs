@@ -1,50 +1,86 @@
-use crate::sys_libc::{self, FdSet, SocketFd};
+use crate::non_blocking_epoll::send_request;
+use crate::report::{Report, TimedResponses};
+use crate::shutdown::{Interrupted, Shutdown};
+use crate::sys_libc::{self, DynFdSet, SockAddr, SocketFd, TcpNoDelay, stats};
+use crate::workload::Workload;
 use crate::{cyan, green};
-use std::{mem, ptr};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::os::fd::{AsFd, AsRawFd};
+use std::time::{Duration, Instant};
 
-pub fn non_blocking_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
+pub fn non_blocking_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
     green!("--- non_blocking_select ---");
+    let server_addr = SockAddr::from(addr);
+    stats::reset();
+    let shutdown = Shutdown::new()?;
+    let request = workload.request();
+
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        let (responses, round_latencies) =
+            run_round(round.len(), &server_addr, &request, workload, &shutdown)?;
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
+    }
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
+}
+
+/// `count` requests at the same time, their responses and latencies
+fn run_round(
+    count: usize,
+    server_addr: &SockAddr,
+    request: &str,
+    workload: &Workload,
+    shutdown: &Shutdown,
+) -> Result<TimedResponses, anyhow::Error> {
     // make request using non-blocking socket
     let mut sockets = vec![];
     // created sockets
-    for i in 0..3 {
-        let sock = sys_libc::create_non_blocking_tcp_socket()?;
+    for _ in 0..count {
+        let sock = sys_libc::create_non_blocking_tcp_socket(server_addr.family())?;
+        sys_libc::set_opt(&sock, TcpNoDelay, true)?; // disable Nagle's algorithm
         sockets.push(sock);
     }
 
-    let server_addr = sys_libc::create_ipv4_sockaddr("127.0.0.1", 3000)?;
-
-    // connect all sockets
+    // connect all sockets, the latencies count from here
+    let started = Instant::now();
     for socket in sockets.iter() {
-        sys_libc::connect(socket, &server_addr)?;
+        sys_libc::connect(socket, server_addr)?;
     }
 
     // wait until all are connected using select
-    wait_for_connections(&sockets)?;
+    wait_for_connections(&sockets, shutdown, workload.timeout)?;
 
-    // send data to all sockets
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-    for socket in sockets.iter() {
-        sys_libc::send(socket, request.as_bytes())?;
-    }
+    // send data to all sockets, a short write waits for the socket to be writable again
+    send_all(&sockets, request.as_bytes(), shutdown, workload.timeout)?;
 
     // receive data from all sockets using non-blocking select
-    let responses = receive_all_non_blocking(&sockets)?;
-
-    // print all responses
-    for (i, response) in responses.iter().enumerate() {
-        println!("Response {}:\n{}", i, String::from_utf8_lossy(response));
-    }
-    println!("Non-blocking select duration: {:?}", start.elapsed());
-    Ok(())
+    receive_all_non_blocking(&sockets, shutdown, workload.timeout, started)
 }
 
-pub fn receive_all_non_blocking(sockets: &[SocketFd]) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+/// Same per phase deadline as the poll and epoll drivers. The latencies are
+/// from `started` to the end of each response.
+pub fn receive_all_non_blocking(
+    sockets: &[SocketFd],
+    shutdown: &Shutdown,
+    timeout: Duration,
+    started: Instant,
+) -> Result<TimedResponses, anyhow::Error> {
     let mut responses = vec![Vec::new(); sockets.len()];
     let mut finished = vec![false; sockets.len()];
-    let mut fd_set = FdSet::new();
-    let max_fd = sockets.iter().map(|s| s.0).max().unwrap();
+    let mut latencies = vec![None; sockets.len()];
+    // grows past FD_SETSIZE, a big --concurrency has fds over 1024
+    let mut fd_set = DynFdSet::new();
+    let max_fd = max_fd(sockets, shutdown);
+    let index = index_by_fd(sockets);
+    // select hands back what is left, so all the waits share one deadline
+    let mut timeout = Some(timeout);
 
     while !finished.iter().all(|&f| f) {
         fd_set.clear();
@@ -52,7 +88,7 @@ pub fn receive_all_non_blocking(sockets: &[SocketFd]) -> Result<Vec<Vec<u8>>, an
 
         for (i, socket) in sockets.iter().enumerate() {
             if !finished[i] {
-                fd_set.set(&socket);
+                fd_set.set(socket);
                 has_active_sockets = true;
             }
         }
@@ -60,23 +96,40 @@ pub fn receive_all_non_blocking(sockets: &[SocketFd]) -> Result<Vec<Vec<u8>>, an
         if !has_active_sockets {
             break;
         }
-        let result = sys_libc::select_read(max_fd + 1, &mut fd_set, None)?;
-
-        if result > 0 {
-            for (i, socket) in sockets.iter().enumerate() {
-                if !finished[i] && fd_set.is_set(&socket) {
-                    let mut temp_buf = [0u8; 4096];
-                    match sys_libc::recv(socket, &mut temp_buf)? {
-                        Some(0) => {
-                            finished[i] = true;
-                            crate::cyan!("Socket {} finished receiving", socket);
-                        }
-                        Some(bytes_received) => {
-                            responses[i].extend_from_slice(&temp_buf[..bytes_received]);
-                        }
-                        None => {} // Would block, continue
-                    }
+        fd_set.set(shutdown);
+        let (result, remaining) = sys_libc::select_read(max_fd + 1, &mut fd_set, timeout)?;
+        if result == 0 {
+            return Err(anyhow::anyhow!("Timeout waiting for data"));
+        }
+        cyan!("{:?} left before the deadline", remaining);
+        timeout = remaining;
+
+        if fd_set.is_set(shutdown) && shutdown.check()?.is_some() {
+            let done = finished.iter().filter(|&&f| f).count();
+            cyan!(
+                "Stopping with {} of {} responses complete",
+                done,
+                sockets.len()
+            );
+            break;
+        }
+        // only the ready fds, no need to ask every socket
+        for fd in fd_set.iter() {
+            let Some(&i) = index.get(&fd) else {
+                continue; // the signalfd, nothing to read yet
+            };
+            let socket = &sockets[i];
+            let mut temp_buf = [0u8; 4096];
+            match sys_libc::recv(socket, &mut temp_buf)? {
+                Some(0) => {
+                    finished[i] = true;
+                    latencies[i] = Some(started.elapsed());
+                    crate::cyan!("Socket {} finished receiving", socket);
                 }
+                Some(bytes_received) => {
+                    responses[i].extend_from_slice(&temp_buf[..bytes_received]);
+                }
+                None => {} // Would block, continue
             }
         }
     }
@@ -85,40 +138,129 @@ pub fn receive_all_non_blocking(sockets: &[SocketFd]) -> Result<Vec<Vec<u8>>, an
         crate::cyan!("Total bytes received from socket {}: {}", i, response.len());
     }
 
-    Ok(responses)
+    Ok((responses, latencies))
 }
 
-pub fn wait_for_connections(sockets: &[SocketFd]) -> Result<(), anyhow::Error> {
-    let mut fd_set = sys_libc::FdSet::new();
-    let max_fd = sockets.iter().map(|s| s.0).max().unwrap();
+pub fn wait_for_connections(
+    sockets: &[SocketFd],
+    shutdown: &Shutdown,
+    timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    let mut fd_set = DynFdSet::new();
+    let mut read_set = DynFdSet::new();
+    let max_fd = max_fd(sockets, shutdown);
+    let index = index_by_fd(sockets);
+    let mut connected = vec![false; sockets.len()];
+    let mut timeout = Some(timeout);
 
-    loop {
+    while !connected.iter().all(|&c| c) {
         fd_set.clear();
-        for socket in sockets {
-            fd_set.set(socket);
+        for (i, socket) in sockets.iter().enumerate() {
+            if !connected[i] {
+                fd_set.set(socket);
+            }
         }
+        read_set.clear();
+        read_set.set(shutdown);
 
-        let result = sys_libc::select_write(max_fd + 1, &mut fd_set, None)?;
-
-        if result > 0 {
-            let mut all_connected = true;
-            for socket in sockets {
-                if fd_set.is_set(socket) {
-                    // Check if connection completed successfully
-                    let error = sys_libc::get_socket_error(socket)?;
-                    if error != 0 {
-                        return Err(anyhow::anyhow!("Connection failed with error: {}", error));
-                    }
-                    crate::cyan!("Socket {} connected successfully", socket);
-                } else {
-                    all_connected = false;
-                }
+        let (result, remaining) = sys_libc::select(
+            max_fd + 1,
+            Some(&mut read_set),
+            Some(&mut fd_set),
+            None,
+            timeout,
+        )?;
+        if result == 0 {
+            return Err(anyhow::anyhow!("Timeout waiting for connections"));
+        }
+        timeout = remaining;
+
+        if read_set.is_set(shutdown)
+            && let Some(signal) = shutdown.check()?
+        {
+            return Err(Interrupted(signal).into());
+        }
+
+        for fd in fd_set.iter() {
+            let i = index[&fd];
+            // Check if connection completed successfully
+            let error = sys_libc::get_socket_error(&sockets[i])?;
+            if error != 0 {
+                return Err(anyhow::anyhow!("Connection failed with error: {}", error));
             }
-            if all_connected {
-                crate::cyan!("All sockets connected!");
-                break;
+            crate::cyan!("Socket {} connected successfully", sockets[i]);
+            connected[i] = true;
+        }
+    }
+    crate::cyan!("All sockets connected!");
+    Ok(())
+}
+
+/// `request` on every socket. What a socket couldn't take right away goes out
+/// once select says it is writable again, from where it stopped.
+pub fn send_all(
+    sockets: &[SocketFd],
+    request: &[u8],
+    shutdown: &Shutdown,
+    timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    let mut write_set = DynFdSet::new();
+    let mut read_set = DynFdSet::new();
+    let max_fd = max_fd(sockets, shutdown);
+    let index = index_by_fd(sockets);
+    let mut sent = vec![0; sockets.len()];
+    for (i, socket) in sockets.iter().enumerate() {
+        sent[i] = send_request(socket, request, 0)?;
+    }
+    let mut timeout = Some(timeout);
+
+    while sent.iter().any(|&n| n < request.len()) {
+        write_set.clear();
+        for (i, socket) in sockets.iter().enumerate() {
+            if sent[i] < request.len() {
+                write_set.set(socket);
             }
         }
+        read_set.clear();
+        read_set.set(shutdown);
+
+        let (result, remaining) = sys_libc::select(
+            max_fd + 1,
+            Some(&mut read_set),
+            Some(&mut write_set),
+            None,
+            timeout,
+        )?;
+        if result == 0 {
+            return Err(anyhow::anyhow!("Timeout sending requests"));
+        }
+        timeout = remaining;
+
+        if read_set.is_set(shutdown)
+            && let Some(signal) = shutdown.check()?
+        {
+            return Err(Interrupted(signal).into());
+        }
+
+        for fd in write_set.iter() {
+            let i = index[&fd];
+            sent[i] = send_request(&sockets[i], request, sent[i])?;
+        }
     }
     Ok(())
 }
+
+/// The signalfd is in the sets too
+fn max_fd(sockets: &[SocketFd], shutdown: &Shutdown) -> i32 {
+    let max_socket = sockets.iter().map(|s| s.0).max().unwrap();
+    max_socket.max(shutdown.as_fd().as_raw_fd())
+}
+
+/// Maps the fds select reports back to our position in `sockets`
+fn index_by_fd(sockets: &[SocketFd]) -> HashMap<i32, usize> {
+    sockets
+        .iter()
+        .enumerate()
+        .map(|(i, socket)| (socket.0, i))
+        .collect()
+}
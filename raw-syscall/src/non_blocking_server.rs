@@ -0,0 +1,368 @@
+//! The server half of the event loop: a non-blocking listener plus the
+//! connections it accepts, all multiplexed with select, poll or epoll.
+//! Answers every request with "hello", like the delay server minus the delay.
+use raw_syscall::shutdown::{SIGNAL_TOKEN, Shutdown};
+use raw_syscall::sys_libc::libc::{POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT};
+use raw_syscall::sys_libc::{
+    self, DynFdSet, Epoll, Errno, Events, Interest, PollFd, ReuseAddr, SockAddr, SocketFd,
+};
+use raw_syscall::workload::Workload;
+use raw_syscall::{cyan, green};
+use std::collections::HashMap;
+use std::io::IoSlice;
+use std::net::SocketAddr;
+use std::os::fd::{AsFd, AsRawFd};
+
+// sent with one writev, never copied into a single buffer
+const HEADERS: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\n";
+const BODY: &[u8] = b"hello";
+const RESPONSE_LEN: usize = HEADERS.len() + BODY.len();
+
+const LISTENER_TOKEN: u64 = u64::MAX - 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Multiplexer {
+    Select,
+    Poll,
+    Epoll,
+}
+
+struct Connection {
+    socket: SocketFd,
+    peer: SockAddr,
+    request: Vec<u8>,
+    /// bytes of the response already sent, None while still reading the request
+    sent: Option<usize>,
+}
+
+impl Connection {
+    fn wants_write(&self) -> bool {
+        self.sent.is_some()
+    }
+}
+
+/// What a ready fd is: select and poll tell by the fd, epoll by the token
+#[derive(Clone, Copy)]
+enum Source {
+    Signal,
+    Listener,
+    /// Keyed by its fd in `connections`
+    Connection(i32),
+}
+
+/// A connection's token is its key in `connections`, its fd
+fn connection_token(socket: &SocketFd) -> u64 {
+    socket.as_raw_fd() as u64
+}
+
+fn source(token: u64) -> Source {
+    match token {
+        SIGNAL_TOKEN => Source::Signal,
+        LISTENER_TOKEN => Source::Listener,
+        token => Source::Connection(token as i32),
+    }
+}
+
+/// What the multiplexer reported for one fd
+struct Ready {
+    source: Source,
+    readable: bool,
+    writable: bool,
+    error: bool,
+}
+
+pub fn run_server(
+    addr: SocketAddr,
+    multiplexer: Multiplexer,
+    workload: &Workload,
+) -> Result<(), anyhow::Error> {
+    green!("--- non_blocking_server ---");
+    let addr = SockAddr::from(addr);
+    let listener = sys_libc::create_non_blocking_tcp_socket(addr.family())?;
+    // restarting the server would otherwise fail while old connections sit in TIME_WAIT
+    sys_libc::set_opt(&listener, ReuseAddr, true)?;
+    sys_libc::bind(&listener, &addr)?;
+    sys_libc::listen(&listener, sys_libc::SOMAXCONN)?;
+    println!("Listening on {}", sys_libc::getsockname(&listener)?);
+    // like the listener, one more fd to wait on
+    let shutdown = Shutdown::new()?;
+
+    // select and poll get their fds anew on every call, only epoll keeps a list
+    let epoll = match multiplexer {
+        Multiplexer::Epoll => Some(Epoll::new()?),
+        Multiplexer::Select | Multiplexer::Poll => None,
+    };
+    let mut events = Events::with_capacity(workload.events_capacity);
+    if let Some(epoll) = &epoll {
+        epoll.add(&listener, LISTENER_TOKEN, Interest::READABLE)?;
+        epoll.add(&shutdown, SIGNAL_TOKEN, Interest::READABLE)?;
+    }
+
+    let mut connections: HashMap<i32, Connection> = HashMap::new();
+    loop {
+        let ready = match (&epoll, multiplexer) {
+            (Some(epoll), _) => wait_epoll(epoll, &mut events)?,
+            (None, Multiplexer::Select) => wait_select(&listener, &shutdown, &connections)?,
+            (None, _) => wait_poll(&listener, &shutdown, &connections)?,
+        };
+
+        for ready in ready {
+            let fd = match ready.source {
+                Source::Signal => {
+                    if shutdown.check()?.is_some() {
+                        stop(&connections);
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Source::Listener => {
+                    accept_all(&listener, &mut connections, epoll.as_ref());
+                    continue;
+                }
+                Source::Connection(fd) => fd,
+            };
+            let Some(conn) = connections.get_mut(&fd) else {
+                continue; // already closed in this round
+            };
+            // one client going wrong (ECONNRESET, EPIPE, ...) only closes that connection
+            let done = match serve(conn, &ready, epoll.as_ref()) {
+                Ok(done) => done,
+                Err(err) => {
+                    println!("Error on connection from {}: {}, closing", conn.peer, err);
+                    connections.remove(&fd);
+                    continue;
+                }
+            };
+            if done {
+                // closing the fd also removes it from the epoll interest list
+                let conn = connections.remove(&fd).unwrap();
+                println!("Served {}", conn.peer);
+            }
+        }
+    }
+}
+
+/// Shows where every open connection got to, dropping them afterwards closes them
+fn stop(connections: &HashMap<i32, Connection>) {
+    println!("Stopping with {} open connections", connections.len());
+    for conn in connections.values() {
+        match conn.sent {
+            Some(sent) => println!(
+                "  {}: {} of {} response bytes sent",
+                conn.peer, sent, RESPONSE_LEN
+            ),
+            None => println!(
+                "  {}: {} request bytes received",
+                conn.peer,
+                conn.request.len()
+            ),
+        }
+    }
+}
+
+/// Returns true once the connection is done with, `ready.error` included
+fn serve(
+    conn: &mut Connection,
+    ready: &Ready,
+    epoll: Option<&Epoll>,
+) -> Result<bool, anyhow::Error> {
+    if ready.error {
+        return Err(anyhow::anyhow!(
+            "{}",
+            Errno::from_raw(sys_libc::get_socket_error(&conn.socket)?)
+        ));
+    }
+    if ready.readable && !conn.wants_write() {
+        let done = read_request(conn)?;
+        if conn.wants_write()
+            && let Some(epoll) = epoll
+        {
+            let token = connection_token(&conn.socket);
+            epoll.modify(&conn.socket, token, Interest::WRITABLE)?;
+        }
+        return Ok(done);
+    }
+    if ready.writable && conn.wants_write() {
+        return write_response(conn);
+    }
+    Ok(false)
+}
+
+/// Accepts until the backlog is empty. A failed accept only costs that client,
+/// the server carries on either way.
+fn accept_all(
+    listener: &SocketFd,
+    connections: &mut HashMap<i32, Connection>,
+    epoll: Option<&Epoll>,
+) {
+    let flags = sys_libc::SOCK_NONBLOCK | sys_libc::SOCK_CLOEXEC;
+    loop {
+        let (socket, peer) = match sys_libc::accept4(listener, flags) {
+            Ok(Some(accepted)) => accepted,
+            Ok(None) => return,
+            // gone before we got to it, or turned away by a firewall: next one
+            Err(err) if matches!(err.kind, Errno::ECONNABORTED | Errno::EPERM) => {
+                println!("Accept failed: {}, skipping it", err);
+                continue;
+            }
+            // out of fds (EMFILE, ENFILE) or anything else: the rest stays in the
+            // backlog until closing connections frees some
+            Err(err) => {
+                println!("Accept failed: {}, trying again later", err);
+                return;
+            }
+        };
+        if let Err(err) = register(&socket, &peer, epoll) {
+            println!("Dropping {}: {}", peer, err);
+            continue;
+        }
+        connections.insert(
+            socket.as_raw_fd(),
+            Connection {
+                socket,
+                peer,
+                request: Vec::new(),
+                sent: None,
+            },
+        );
+    }
+}
+
+fn register(
+    socket: &SocketFd,
+    peer: &SockAddr,
+    epoll: Option<&Epoll>,
+) -> Result<(), anyhow::Error> {
+    println!("Accepted {} -> {}", peer, sys_libc::getsockname(socket)?);
+    if let Some(epoll) = epoll {
+        epoll.add(socket, connection_token(socket), Interest::READABLE)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the client went away before finishing its request
+fn read_request(conn: &mut Connection) -> Result<bool, anyhow::Error> {
+    let mut buf = [0u8; 4096];
+    match sys_libc::recv(&conn.socket, &mut buf)? {
+        Some(0) => return Ok(true),
+        Some(n) => conn.request.extend_from_slice(&buf[..n]),
+        None => return Ok(false),
+    }
+    if conn.request.windows(4).any(|w| w == b"\r\n\r\n") {
+        cyan!(
+            "Request from {}:\n{}",
+            conn.peer,
+            String::from_utf8_lossy(&conn.request)
+        );
+        conn.sent = Some(0);
+    }
+    Ok(false)
+}
+
+/// Returns true once the whole response is out
+fn write_response(conn: &mut Connection) -> Result<bool, anyhow::Error> {
+    let sent = conn.sent.unwrap_or(0);
+    let mut bufs = [IoSlice::new(HEADERS), IoSlice::new(BODY)];
+    // skip what went out already, a partial write can stop in the headers
+    let mut rest = &mut bufs[..];
+    IoSlice::advance_slices(&mut rest, sent);
+    if let Some(n) = sys_libc::writev(&conn.socket, rest)? {
+        cyan!("Sent {} bytes with writev", n);
+        conn.sent = Some(sent + n);
+    }
+    Ok(conn.sent == Some(RESPONSE_LEN))
+}
+
+fn wait_select(
+    listener: &SocketFd,
+    shutdown: &Shutdown,
+    connections: &HashMap<i32, Connection>,
+) -> Result<Vec<Ready>, anyhow::Error> {
+    // a busy server can go past FD_SETSIZE, let the sets grow with the fds
+    let mut read_set = DynFdSet::new();
+    let mut write_set = DynFdSet::new();
+    read_set.set(listener);
+    read_set.set(shutdown);
+    let mut max_fd = listener.as_raw_fd().max(shutdown.as_fd().as_raw_fd());
+    for conn in connections.values() {
+        if conn.wants_write() {
+            write_set.set(&conn.socket);
+        } else {
+            read_set.set(&conn.socket);
+        }
+        max_fd = max_fd.max(conn.socket.as_raw_fd());
+    }
+    sys_libc::select(
+        max_fd + 1,
+        Some(&mut read_set),
+        Some(&mut write_set),
+        None,
+        None,
+    )?;
+
+    // a connection waits for either reading or writing, so an fd shows up in one set at most
+    let readable = read_set.iter().map(|fd| Ready {
+        source: source_of(fd, listener, shutdown),
+        readable: true,
+        writable: false,
+        error: false,
+    });
+    let writable = write_set.iter().map(|fd| Ready {
+        source: source_of(fd, listener, shutdown),
+        readable: false,
+        writable: true,
+        error: false,
+    });
+    Ok(readable.chain(writable).collect())
+}
+
+fn wait_poll(
+    listener: &SocketFd,
+    shutdown: &Shutdown,
+    connections: &HashMap<i32, Connection>,
+) -> Result<Vec<Ready>, anyhow::Error> {
+    let mut poll_fds = vec![PollFd::new(listener, POLLIN), PollFd::new(shutdown, POLLIN)];
+    for conn in connections.values() {
+        let events = if conn.wants_write() { POLLOUT } else { POLLIN };
+        poll_fds.push(PollFd::new(&conn.socket, events));
+    }
+    sys_libc::poll(&mut poll_fds, -1)?;
+
+    let ready = poll_fds
+        .iter()
+        .filter(|pfd| pfd.revents() != 0)
+        .map(|pfd| Ready {
+            // SAFETY: the fd is only used as a key while connections are alive
+            source: source_of(unsafe { pfd.fd() }, listener, shutdown),
+            readable: pfd.revents() & POLLIN != 0,
+            writable: pfd.revents() & POLLOUT != 0,
+            error: pfd.revents() & (POLLERR | POLLHUP | POLLNVAL) != 0,
+        })
+        .collect();
+    Ok(ready)
+}
+
+fn wait_epoll(epoll: &Epoll, events: &mut Events) -> Result<Vec<Ready>, anyhow::Error> {
+    epoll.wait(events, None)?;
+    let ready = events
+        .iter()
+        .map(|(token, readiness)| Ready {
+            source: source(token),
+            readable: readiness.is_readable(),
+            writable: readiness.is_writable(),
+            error: readiness.is_error() || readiness.is_hup(),
+        })
+        .collect();
+    Ok(ready)
+}
+
+/// The fd select or poll reported, as what it is
+fn source_of(fd: i32, listener: &SocketFd, shutdown: &Shutdown) -> Source {
+    if fd == listener.as_raw_fd() {
+        Source::Listener
+    } else if fd == shutdown.as_fd().as_raw_fd() {
+        Source::Signal
+    } else {
+        Source::Connection(fd)
+    }
+}
@@ -0,0 +1,128 @@
+//! Datagrams through epoll, against the UDP echo endpoint of the delay server.
+//!
+//! Compared to the TCP drivers there is no connect phase (the first `sendto`
+//! picks a local port), every `sendto` is exactly one `recvfrom` on the other
+//! side, and nothing guarantees the echo comes back at all.
+use crate::report::Report;
+use crate::shutdown::{SIGNAL_TOKEN, Shutdown};
+use crate::sys_libc::{self, Epoll, Errno, Events, Interest, SockAddr, SocketFd, stats};
+use crate::workload::Workload;
+use crate::{cyan, green};
+use std::net::SocketAddr;
+
+/// Bigger than any UDP payload (65507 bytes over IPv4)
+const OVERSIZED_DATAGRAM: usize = 70_000;
+/// Small on purpose so the last echo doesn't fit
+const RECV_BUF_SIZE: usize = 512;
+
+/// `--count` is the number of sockets here, each sends two datagrams
+pub fn non_blocking_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = std::time::Instant::now();
+    green!("--- non_blocking_udp_epoll ---");
+    stats::reset();
+    let epoll = Epoll::new()?;
+    let server_addr = SockAddr::from(addr);
+
+    let mut events = Events::with_capacity(workload.events_capacity);
+    let shutdown = Shutdown::new()?;
+    epoll.add(&shutdown, SIGNAL_TOKEN, Interest::READABLE)?;
+
+    let mut sockets = vec![];
+    for idx in 0..workload.count as u64 {
+        let socket = sys_libc::create_non_blocking_udp_socket(server_addr.family())?;
+        // level triggered and read only: a UDP socket is writable unless its buffer is full
+        epoll.add(&socket, idx, Interest::READABLE)?;
+        sockets.push(socket);
+    }
+
+    show_emsgsize(&sockets[0], &server_addr)?;
+
+    // two datagrams per socket, they come back as two separate messages
+    let mut expected = 0;
+    for (idx, socket) in sockets.iter().enumerate() {
+        for part in ["a", "b"] {
+            let message = format!("datagram {}{}", idx, part);
+            send_datagram(socket, message.as_bytes(), &server_addr)?;
+            expected += 1;
+        }
+    }
+    // one more that is bigger than our receive buffer
+    let last = sockets.last().expect("--count is at least 1");
+    send_datagram(last, &[b'x'; RECV_BUF_SIZE * 2], &server_addr)?;
+    expected += 1;
+
+    let mut echoes = vec![];
+    let mut buf = [0u8; RECV_BUF_SIZE];
+    'events: while echoes.len() < expected {
+        if epoll.wait(&mut events, Some(workload.timeout))? == 0 {
+            // UDP drops silently, there is no connection to report it
+            return Err(anyhow::anyhow!(
+                "Timeout waiting for echoes, {} of {} datagrams lost",
+                expected - echoes.len(),
+                expected
+            ));
+        }
+        for (token, readiness) in events.iter() {
+            if token == SIGNAL_TOKEN {
+                if shutdown.check()?.is_some() {
+                    cyan!("Stopping with {} of {} echoes back", echoes.len(), expected);
+                    break 'events;
+                }
+                continue;
+            }
+            let idx = token as usize;
+            if readiness.is_error() {
+                let error = sys_libc::get_socket_error(&sockets[idx])?;
+                cyan!("Socket {} error: {}", sockets[idx], Errno::from_raw(error));
+                continue;
+            }
+            // drain: one recvfrom per datagram
+            while let Some((n, from)) = sys_libc::recvfrom(&sockets[idx], &mut buf)? {
+                if n > buf.len() {
+                    cyan!(
+                        "Echo on socket {} from {}: {} bytes, only {} fit in the buffer, the rest is gone",
+                        idx,
+                        from,
+                        n,
+                        buf.len()
+                    );
+                    echoes.push(buf.to_vec());
+                } else {
+                    cyan!(
+                        "Echo on socket {} from {}: {}",
+                        idx,
+                        from,
+                        String::from_utf8_lossy(&buf[..n])
+                    );
+                    echoes.push(buf[..n].to_vec());
+                }
+            }
+        }
+    }
+
+    Ok(Report::new(echoes, vec![], start))
+}
+
+fn send_datagram(socket: &SocketFd, payload: &[u8], addr: &SockAddr) -> Result<(), anyhow::Error> {
+    match sys_libc::sendto(socket, payload, addr)? {
+        Some(_) => Ok(()),
+        // not worth waiting for EPOLLOUT here, a full send buffer means we flood the loopback
+        None => Err(anyhow::anyhow!("Send buffer full on {}", socket)),
+    }
+}
+
+/// A datagram can't be split like a TCP stream, too big is an error, not a partial send
+fn show_emsgsize(socket: &SocketFd, addr: &SockAddr) -> Result<(), anyhow::Error> {
+    let payload = vec![0u8; OVERSIZED_DATAGRAM];
+    match sys_libc::sendto(socket, &payload, addr) {
+        Err(err) if err.kind == Errno::EMSGSIZE => {
+            cyan!("Sending {} bytes in one datagram: {}", payload.len(), err);
+            Ok(())
+        }
+        Err(err) => Err(err.into()),
+        Ok(n) => {
+            cyan!("Unexpectedly sent {:?} bytes in one datagram", n);
+            Ok(())
+        }
+    }
+}
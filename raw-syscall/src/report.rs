@@ -0,0 +1,47 @@
+//! What a client driver hands back, the CLI is what prints it.
+use crate::shutdown::Shutdown;
+use crate::sys_libc::stats;
+use std::time::{Duration, Instant};
+
+/// Responses and their latencies, one each per request like in `Report`
+pub type TimedResponses = (Vec<Vec<u8>>, Vec<Option<Duration>>);
+
+pub struct Report {
+    /// One per request in order, partial or empty when a shutdown or a timer
+    /// stopped it. For `non_blocking_udp_epoll` the echoes as they came back.
+    pub responses: Vec<Vec<u8>>,
+    /// One per request like `responses`, from its connect to the end of its
+    /// response, None if it didn't get there. Empty for `non_blocking_udp_epoll`.
+    pub latencies: Vec<Option<Duration>>,
+    pub elapsed: Duration,
+    /// From `stats`, process wide: only meaningful with one driver running at a time.
+    /// The std and mio drivers go around `sys_libc`, they have next to none.
+    pub wakeups: u64,
+    pub syscalls: u64,
+    /// The signal that stopped the driver early, the responses are what it had by then
+    pub interrupted: Option<i32>,
+}
+
+impl Report {
+    /// Timed from `start`, the counters are what `stats` has by now
+    pub(crate) fn new(
+        responses: Vec<Vec<u8>>,
+        latencies: Vec<Option<Duration>>,
+        start: Instant,
+    ) -> Self {
+        Report {
+            responses,
+            latencies,
+            elapsed: start.elapsed(),
+            wakeups: stats::wakeups(),
+            syscalls: stats::syscalls(),
+            interrupted: None,
+        }
+    }
+
+    /// Records whether `shutdown` cut the run short
+    pub(crate) fn stopped_by(mut self, shutdown: &Shutdown) -> Self {
+        self.interrupted = shutdown.signal();
+        self
+    }
+}
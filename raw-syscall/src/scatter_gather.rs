@@ -0,0 +1,147 @@
+//! Scatter/gather I/O and control messages, over sockets we make ourselves, no server needed.
+//!
+//! - `writev`/`readv`: HTTP headers and body go out from their own buffers in
+//!   one call, and come in split over two buffers the same way.
+//! - `SCM_RIGHTS`: an eventfd is sent over a unix socket pair. The receiver
+//!   gets a new fd number for the same kernel object, notifying one side is
+//!   seen on the other.
+//! - `SO_TIMESTAMP`: a UDP datagram comes with the time the kernel got it,
+//!   which tells how long it sat in the socket before we read it.
+use raw_syscall::sys_libc::libc::AF_UNIX;
+use raw_syscall::sys_libc::{
+    self, ControlBuffer, ControlMessage, EventFd, ReceivedControl, SOCK_CLOEXEC, SOCK_STREAM,
+    SockAddr, Timestamp, cmsg_space, stats,
+};
+use raw_syscall::{cyan, green};
+use std::io::{IoSlice, IoSliceMut};
+use std::net::SocketAddr;
+use std::os::fd::AsFd;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HEADERS: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+const BODY: &[u8] = b"hello";
+
+pub fn scatter_gather() -> Result<(), anyhow::Error> {
+    green!("--- scatter_gather ---");
+    stats::reset();
+    writev_readv()?;
+    pass_fd()?;
+    udp_timestamp()?;
+    cyan!("{} syscalls", stats::syscalls());
+    Ok(())
+}
+
+fn writev_readv() -> Result<(), anyhow::Error> {
+    green!("writev/readv");
+    let (left, right) = sys_libc::socketpair(AF_UNIX, SOCK_STREAM | SOCK_CLOEXEC)?;
+    let bufs = [IoSlice::new(HEADERS), IoSlice::new(BODY)];
+    let written = sys_libc::writev(&left, &bufs)?.unwrap_or(0);
+    println!(
+        "writev: {} bytes from {} + {} byte buffers",
+        written,
+        HEADERS.len(),
+        BODY.len()
+    );
+
+    // where the first buffer ends doesn't care where the headers did
+    let mut first = [0u8; 20];
+    let mut second = [0u8; 64];
+    let mut bufs = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+    let read = sys_libc::readv(&right, &mut bufs)?.unwrap_or(0);
+    let in_second = read.saturating_sub(first.len());
+    println!("readv: {} bytes", read);
+    println!(
+        "  first buffer:  {:?}",
+        String::from_utf8_lossy(&first[..read.min(first.len())])
+    );
+    println!(
+        "  second buffer: {:?}",
+        String::from_utf8_lossy(&second[..in_second])
+    );
+    Ok(())
+}
+
+fn pass_fd() -> Result<(), anyhow::Error> {
+    green!("SCM_RIGHTS");
+    let (left, right) = sys_libc::socketpair(AF_UNIX, SOCK_STREAM | SOCK_CLOEXEC)?;
+    let eventfd = EventFd::new()?;
+    // at least one byte of data has to go along on a stream socket
+    let fds = [eventfd.as_fd()];
+    sys_libc::sendmsg(
+        &left,
+        &[IoSlice::new(b"fd")],
+        None,
+        &[ControlMessage::Rights(&fds)],
+    )?;
+
+    let mut data = [0u8; 2];
+    let mut control = ControlBuffer::with_capacity(cmsg_space(size_of::<i32>()));
+    let Some(msg) = sys_libc::recvmsg(&right, &mut [IoSliceMut::new(&mut data)], &mut control)?
+    else {
+        return Err(anyhow::anyhow!("Nothing received on {}", right));
+    };
+    if msg.control_truncated {
+        return Err(anyhow::anyhow!("Control data truncated, fds were lost"));
+    }
+    for message in msg.control {
+        match message {
+            ReceivedControl::Rights(fds) => {
+                for fd in fds {
+                    let received = EventFd::from(fd);
+                    println!("Sent {}, received it as {}", eventfd, received);
+                    received.notify()?;
+                    println!(
+                        "Notified {}, {} drains {}: same eventfd",
+                        received,
+                        eventfd,
+                        eventfd.drain()?
+                    );
+                }
+            }
+            other => println!("Unexpected control message {:?}", other),
+        }
+    }
+    Ok(())
+}
+
+fn udp_timestamp() -> Result<(), anyhow::Error> {
+    green!("SO_TIMESTAMP");
+    let loopback = SockAddr::from(SocketAddr::from(([127, 0, 0, 1], 0)));
+    let receiver = sys_libc::create_udp_socket(loopback.family())?;
+    sys_libc::bind(&receiver, &loopback)?;
+    sys_libc::set_opt(&receiver, Timestamp, true)?;
+    let receiver_addr = sys_libc::getsockname(&receiver)?;
+    let sender = sys_libc::create_udp_socket(loopback.family())?;
+
+    // a datagram from two buffers is still one datagram
+    let parts = [IoSlice::new(b"ping "), IoSlice::new(b"with a timestamp")];
+    sys_libc::sendmsg(&sender, &parts, Some(&receiver_addr), &[])?;
+    std::thread::sleep(Duration::from_millis(20));
+
+    let mut data = [0u8; 64];
+    let mut control = ControlBuffer::with_capacity(cmsg_space(16));
+    let Some(msg) = sys_libc::recvmsg(&receiver, &mut [IoSliceMut::new(&mut data)], &mut control)?
+    else {
+        return Err(anyhow::anyhow!("Nothing received on {}", receiver));
+    };
+    let from = msg.from.map_or("?".to_string(), |from| from.to_string());
+    println!(
+        "Received {:?} from {}",
+        String::from_utf8_lossy(&data[..msg.bytes]),
+        from
+    );
+    for message in msg.control {
+        match message {
+            ReceivedControl::Timestamp(received_at) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+                println!(
+                    "Kernel got it at {:?} since the epoch, {:?} before we read it",
+                    received_at,
+                    now.saturating_sub(received_at)
+                );
+            }
+            other => println!("Unexpected control message {:?}", other),
+        }
+    }
+    Ok(())
+}
@@ -1,26 +1,38 @@
 use crate::{
     green,
-    sys_libc::{self, SocketFd},
+    report::Report,
+    sys_libc::{self, RcvTimeo, SockAddr, SocketFd, stats},
+    workload::Workload,
 };
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
-pub fn sequential_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
+/// One request after the other, `--concurrency` means nothing here
+pub fn sequential_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
     green!("--- sequential ---");
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    stats::reset();
+    let request = workload.request();
 
-    // run 3 times
-    for i in 0..3 {
-        let resp = make_request("127.0.0.1", 3000, request)?;
-        println!("Response:\n{}", String::from_utf8_lossy(&resp));
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for _ in 0..workload.count {
+        let started = Instant::now();
+        received.push(make_request(addr, &request, workload.timeout)?);
+        latencies.push(Some(started.elapsed()));
     }
-
-    println!("Sequential duration: {:?}", start.elapsed());
-    Ok(())
+    Ok(Report::new(received, latencies, start))
 }
 
-pub fn make_request(addr: &str, port: u16, request: &str) -> Result<Vec<u8>, anyhow::Error> {
-    let sockfd = sys_libc::create_tcp_socket()?;
-    let server_addr = sys_libc::create_ipv4_sockaddr(addr, port)?;
+pub fn make_request(
+    addr: SocketAddr,
+    request: &str,
+    timeout: Duration,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let server_addr = SockAddr::from(addr);
+    let sockfd = sys_libc::create_tcp_socket(server_addr.family())?;
+    // blocking recv gives up after this, connect and send don't look at it
+    sys_libc::set_opt(&sockfd, RcvTimeo, Some(timeout))?;
     sys_libc::connect(&sockfd, &server_addr)?;
     sys_libc::send(&sockfd, request.as_bytes())?;
     let response = receive_all(&sockfd)?;
@@ -31,11 +43,9 @@ pub fn receive_all(sockfd: &SocketFd) -> Result<Vec<u8>, anyhow::Error> {
     let mut buffer = Vec::new();
     let mut temp_buf = [0u8; 4096];
     loop {
-        let bytes_received = sys_libc::recv(sockfd, &mut temp_buf)?;
-        if bytes_received.is_none() {
-            continue; // would block, try again
-        }
-        let bytes_received = bytes_received.unwrap();
+        // a blocking socket only "would block" once SO_RCVTIMEO runs out
+        let bytes_received = sys_libc::recv(sockfd, &mut temp_buf)?
+            .ok_or_else(|| anyhow::anyhow!("Timeout waiting for the response"))?;
         if bytes_received == 0 {
             break; // finished receiving
         }
@@ -1,24 +1,36 @@
 use std::{
     io::{Read, Write},
-    net::TcpStream,
+    net::{SocketAddr, TcpStream},
+    time::{Duration, Instant},
 };
 
 use crate::green;
+use crate::report::Report;
+use crate::sys_libc::stats;
+use crate::workload::Workload;
 
-pub fn sequential_calls() -> Result<(), anyhow::Error> {
-    let start = std::time::Instant::now();
+pub fn sequential_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
     green!("--- sequential_std ---");
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-    for i in 0..3 {
-        let resp = make_request("127.0.0.1", 3000, request)?;
-        println!("Response:\n{}", String::from_utf8_lossy(&resp));
+    stats::reset();
+    let request = workload.request();
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for _ in 0..workload.count {
+        let started = Instant::now();
+        received.push(make_request(addr, &request, workload.timeout)?);
+        latencies.push(Some(started.elapsed()));
     }
-    println!("Sequential duration: {:?}", start.elapsed());
-    Ok(())
+    Ok(Report::new(received, latencies, start))
 }
 
-pub fn make_request(addr: &str, port: u16, request: &str) -> Result<Vec<u8>, anyhow::Error> {
-    let mut stream = TcpStream::connect((addr, port))?;
+pub fn make_request(
+    addr: SocketAddr,
+    request: &str,
+    timeout: Duration,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
     stream.write_all(request.as_bytes())?;
     let mut response = Vec::new();
     stream.read_to_end(&mut response)?;
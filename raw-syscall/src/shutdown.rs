@@ -0,0 +1,91 @@
+//! Ctrl-C for the event loops. SIGINT and SIGTERM are blocked and arrive on a
+//! signalfd instead, which the loops watch next to their sockets. When it fires
+//! they stop, show what they got so far and return, the sockets are closed by
+//! their `Drop` on the way out.
+use crate::cyan;
+use crate::sys_libc::libc::{SIG_BLOCK, SIG_SETMASK, SIGINT, SIGTERM};
+use crate::sys_libc::{self, SigSet, SignalFd, SysError, signal_name};
+use std::cell::Cell;
+use std::fmt::Display;
+use std::os::fd::{AsFd, BorrowedFd};
+
+/// Token for the signalfd in the epoll based loops, the sockets use their index
+pub const SIGNAL_TOKEN: u64 = u64::MAX;
+
+pub struct Shutdown {
+    fd: SignalFd,
+    old_mask: SigSet,
+    signal: Cell<Option<i32>>,
+}
+
+impl Shutdown {
+    /// Blocks SIGINT and SIGTERM for this thread (and the threads it spawns from now on).
+    /// Keep it on this thread: its `Drop` unblocks them for the thread it runs on.
+    pub fn new() -> Result<Self, SysError> {
+        let mask = signals();
+        let old_mask = sys_libc::sigprocmask(SIG_BLOCK, Some(&mask))?;
+        let fd = SignalFd::new(&mask)?;
+        Ok(Shutdown {
+            fd,
+            old_mask,
+            signal: Cell::new(None),
+        })
+    }
+
+    /// The signal once one came in, call it when the fd is readable
+    pub fn check(&self) -> Result<Option<i32>, SysError> {
+        let signal = self.fd.read()?;
+        if let Some(signal) = signal {
+            cyan!("Got {}, shutting down", signal_name(signal));
+            self.signal.set(Some(signal));
+        }
+        Ok(signal)
+    }
+
+    /// Whether `check` saw a signal, for the loops that go in rounds to not start another
+    pub fn requested(&self) -> bool {
+        self.signal.get().is_some()
+    }
+
+    /// The signal `check` saw, if any
+    pub fn signal(&self) -> Option<i32> {
+        self.signal.get()
+    }
+
+    /// A signalfd of its own for a thread spawned after `new`, to watch the same
+    /// signals while this `Shutdown` stays where it was made
+    pub fn watcher(&self) -> Result<SignalFd, SysError> {
+        SignalFd::new(&signals())
+    }
+}
+
+fn signals() -> SigSet {
+    let mut mask = SigSet::empty();
+    mask.add(SIGINT).add(SIGTERM);
+    mask
+}
+
+impl AsFd for Shutdown {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for Shutdown {
+    /// A signal that came in after the last `check` is delivered now, the default way
+    fn drop(&mut self) {
+        let _ = sys_libc::sigprocmask(SIG_SETMASK, Some(&self.old_mask));
+    }
+}
+
+/// For loops stopped before they had anything to show, main exits cleanly on it
+#[derive(Debug)]
+pub struct Interrupted(pub i32);
+
+impl Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Interrupted by {}", signal_name(self.0))
+    }
+}
+
+impl std::error::Error for Interrupted {}
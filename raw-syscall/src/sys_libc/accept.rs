@@ -0,0 +1,35 @@
+use super::libc::{sockaddr, sockaddr_storage};
+use super::retry::restartable;
+use super::{Errno, SockAddr, SocketFd, SysError};
+use crate::cyan;
+use std::mem;
+
+/// Accepts a pending connection on a listening socket.
+/// `flags` are passed to the new socket, usually `SOCK_NONBLOCK | SOCK_CLOEXEC`.
+/// If no connection is waiting on a non-blocking listener, returns Ok(None).
+pub fn accept4(sockfd: &SocketFd, flags: i32) -> Result<Option<(SocketFd, SockAddr)>, SysError> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = 0;
+    let accepted = restartable(|| {
+        len = mem::size_of::<sockaddr_storage>() as u32;
+        let addr = &mut storage as *mut sockaddr_storage as *mut sockaddr;
+        let fd = unsafe { syscall!(accept4(sockfd.0, addr, &mut len, flags)) };
+        if fd == -1 {
+            return Err(SysError::last_os_error("accept4"));
+        }
+        Ok(SocketFd(fd))
+    });
+    match accepted {
+        Err(err) if err.is_would_block() => {
+            cyan!("Would block, no pending connection on {}", sockfd);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+        Ok(fd) => {
+            let peer = SockAddr::from_storage(&storage, len)
+                .ok_or(SysError::new("accept4", Errno::EAFNOSUPPORT.raw()))?;
+            cyan!("Accepted {} from {}", fd, peer);
+            Ok(Some((fd, peer)))
+        }
+    }
+}
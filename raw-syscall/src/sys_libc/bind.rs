@@ -0,0 +1,11 @@
+use super::{SockAddr, SocketFd, SysError};
+use crate::cyan;
+
+pub fn bind(sockfd: &SocketFd, addr: &SockAddr) -> Result<(), SysError> {
+    let result = unsafe { syscall!(bind(sockfd.0, addr.as_ptr(), addr.len())) };
+    if result == -1 {
+        return Err(SysError::last_os_error("bind"));
+    }
+    cyan!("Bound {} to {}", sockfd, addr);
+    Ok(())
+}
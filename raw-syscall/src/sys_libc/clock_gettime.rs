@@ -0,0 +1,17 @@
+use super::SysError;
+use super::libc::timespec;
+use std::time::Duration;
+
+/// Current time of `clockid` (`CLOCK_MONOTONIC`, ...) since that clock's epoch.
+/// This is what absolute timerfd deadlines are measured against.
+pub fn clock_gettime(clockid: i32) -> Result<Duration, SysError> {
+    let mut ts = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let result = unsafe { syscall!(clock_gettime(clockid, &mut ts)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("clock_gettime"));
+    }
+    Ok(Duration::from(ts))
+}
@@ -0,0 +1,157 @@
+//! Control messages (ancillary data) for `sendmsg`/`recvmsg`: typed data
+//! that travels next to the bytes instead of in them. Laid out the way the
+//! CMSG_* macros do it: a `cmsghdr`, the data, padding up to 8 bytes, the
+//! next `cmsghdr`, ...
+use super::libc::{SCM_RIGHTS, SCM_TIMESTAMP, SOL_SOCKET, cmsghdr, timeval};
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+/// What `sendmsg` can attach
+pub enum ControlMessage<'a> {
+    /// Duplicates the fds into the receiving process, unix sockets only
+    Rights(&'a [BorrowedFd<'a>]),
+}
+
+/// What `recvmsg` found
+#[derive(Debug)]
+pub enum ReceivedControl {
+    /// New fds in this process for the ones that were sent, close-on-exec
+    Rights(Vec<OwnedFd>),
+    /// When the kernel received the data (`SO_TIMESTAMP`), since the Unix epoch
+    Timestamp(Duration),
+    /// Anything else, left as it came
+    Other { level: i32, kind: i32 },
+}
+
+/// `CMSG_SPACE`: room one control message with `data_len` bytes takes up, padding included
+pub fn cmsg_space(data_len: usize) -> usize {
+    align(size_of::<cmsghdr>()) + align(data_len)
+}
+
+/// `CMSG_LEN`: what goes into `cmsg_len`, no padding after the data
+fn cmsg_len(data_len: usize) -> usize {
+    align(size_of::<cmsghdr>()) + data_len
+}
+
+fn align(len: usize) -> usize {
+    len.next_multiple_of(size_of::<usize>())
+}
+
+/// Room for control messages, aligned for `cmsghdr` like the kernel wants it
+pub struct ControlBuffer {
+    buf: Vec<u64>,
+    /// bytes in use, what the kernel filled in on receive
+    len: usize,
+}
+
+impl ControlBuffer {
+    /// Add up `cmsg_space` of every message expected. Too small and `recvmsg`
+    /// reports the control data truncated, fds that didn't fit are closed.
+    pub fn with_capacity(bytes: usize) -> Self {
+        ControlBuffer {
+            buf: vec![0; bytes.div_ceil(8)],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn encode(messages: &[ControlMessage]) -> Self {
+        let space = messages
+            .iter()
+            .map(|message| match message {
+                ControlMessage::Rights(fds) => cmsg_space(size_of_val(*fds)),
+            })
+            .sum();
+        let mut control = ControlBuffer::with_capacity(space);
+        let bytes = control.as_bytes_mut();
+        let mut offset = 0;
+        for message in messages {
+            match message {
+                ControlMessage::Rights(fds) => {
+                    let data: Vec<u8> = fds
+                        .iter()
+                        .flat_map(|fd| fd.as_raw_fd().to_ne_bytes())
+                        .collect();
+                    offset += put(&mut bytes[offset..], SOL_SOCKET, SCM_RIGHTS, &data);
+                }
+            }
+        }
+        control.len = offset;
+        control
+    }
+
+    pub(crate) fn decode(&self) -> Vec<ReceivedControl> {
+        let bytes = &self.as_bytes()[..self.len];
+        let header_len = align(size_of::<cmsghdr>());
+        let mut messages = vec![];
+        let mut offset = 0;
+        while offset + header_len <= bytes.len() {
+            // SAFETY: in bounds, the buffer is aligned and so is every header in it
+            let header = unsafe { *(bytes[offset..].as_ptr() as *const cmsghdr) };
+            if header.cmsg_len < header_len {
+                break;
+            }
+            // a truncated last message can claim more than there is
+            let end = (offset + header.cmsg_len).min(bytes.len());
+            let data = &bytes[offset + header_len..end];
+            messages.push(match (header.cmsg_level, header.cmsg_type) {
+                (SOL_SOCKET, SCM_RIGHTS) => ReceivedControl::Rights(
+                    data.chunks_exact(size_of::<i32>())
+                        // SAFETY: the kernel just opened them for us
+                        .map(|fd| unsafe {
+                            OwnedFd::from_raw_fd(i32::from_ne_bytes(fd.try_into().unwrap()))
+                        })
+                        .collect(),
+                ),
+                (SOL_SOCKET, SCM_TIMESTAMP) if data.len() >= size_of::<timeval>() => {
+                    let tv = unsafe { (data.as_ptr() as *const timeval).read_unaligned() };
+                    ReceivedControl::Timestamp(Duration::from(tv))
+                }
+                (level, kind) => ReceivedControl::Other { level, kind },
+            });
+            offset += align(header.cmsg_len);
+        }
+        messages
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buf.as_mut_ptr() as *mut u8
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.buf.len() * size_of::<u64>()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn set_len(&mut self, len: usize) {
+        self.len = len.min(self.capacity());
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: u64s are plain bytes
+        unsafe { std::slice::from_raw_parts(self.buf.as_ptr() as *const u8, self.capacity()) }
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        let capacity = self.capacity();
+        // SAFETY: u64s are plain bytes
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), capacity) }
+    }
+}
+
+/// Writes one message at the start of `bytes`, returns the space it took
+fn put(bytes: &mut [u8], level: i32, kind: i32, data: &[u8]) -> usize {
+    let header = cmsghdr {
+        cmsg_len: cmsg_len(data.len()),
+        cmsg_level: level,
+        cmsg_type: kind,
+    };
+    // SAFETY: `encode` made room for it, every message starts aligned
+    unsafe { *(bytes.as_mut_ptr() as *mut cmsghdr) = header };
+    let header_len = align(size_of::<cmsghdr>());
+    bytes[header_len..header_len + data.len()].copy_from_slice(data);
+    cmsg_space(data.len())
+}
@@ -1,23 +1,24 @@
-use super::{SocketFd, libc};
+use super::retry::restartable;
+use super::{Errno, SockAddr, SocketFd, SysError};
 use crate::cyan;
-use libc::{sockaddr, sockaddr_in};
-use std::mem;
 
-pub fn connect(sockfd: &SocketFd, addr: &sockaddr_in) -> Result<(), anyhow::Error> {
-    let result = unsafe {
-        let addr = addr as *const sockaddr_in as *const sockaddr;
-        let len = mem::size_of::<sockaddr_in>() as u32;
-        libc::connect(sockfd.0, addr, len)
-    };
-    if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        if errno.raw_os_error() == Some(libc::EINPROGRESS) {
+pub fn connect(sockfd: &SocketFd, addr: &SockAddr) -> Result<(), SysError> {
+    // Calling connect again on a blocking socket after EINTR waits for the
+    // handshake that is already in flight, so restarting is safe here.
+    let result = restartable(|| {
+        let result = unsafe { syscall!(connect(sockfd.0, addr.as_ptr(), addr.len())) };
+        if result == -1 {
+            return Err(SysError::last_os_error("connect"));
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        if err.kind == Errno::EINPROGRESS {
             cyan!("Non-blocking connect in progress for {}", sockfd);
             return Ok(());
-        } else {
-            return Err(anyhow::anyhow!("Failed to connect {}: {}", sockfd, errno));
         }
+        return Err(err);
     }
-    cyan!("Connect {} to {}", sockfd, addr.sin_addr);
+    cyan!("Connect {} to {}", sockfd, addr);
     Ok(())
 }
@@ -0,0 +1,212 @@
+//! DNS messages (RFC 1035), just enough to ask for A and AAAA records and read
+//! the answers. Pure byte shuffling, no syscalls.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+pub const RCODE_NXDOMAIN: u8 = 3;
+
+/// The header flags we send: a standard query with recursion desired
+const FLAGS_RD: u16 = 0x0100;
+const FLAG_QR: u16 = 0x8000;
+const FLAG_TC: u16 = 0x0200;
+
+pub struct DnsResponse {
+    pub id: u16,
+    pub rcode: u8,
+    /// Didn't fit in the datagram, the full answer needs TCP
+    pub truncated: bool,
+    /// A and AAAA records, CNAMEs on the way are followed by the server and skipped here
+    pub addrs: Vec<IpAddr>,
+}
+
+/// One question for `name`, `qtype` is `TYPE_A` or `TYPE_AAAA`
+pub fn query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, anyhow::Error> {
+    let mut msg = Vec::with_capacity(12 + name.len() + 6);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&FLAGS_RD.to_be_bytes());
+    // one question, no answer, authority or additional records
+    msg.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(anyhow::anyhow!("Invalid hostname {}", name));
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+pub fn parse_response(msg: &[u8]) -> Result<DnsResponse, anyhow::Error> {
+    let header = msg
+        .get(..12)
+        .ok_or_else(|| anyhow::anyhow!("DNS response too short"))?;
+    let field = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]);
+    let flags = field(2);
+    if flags & FLAG_QR == 0 {
+        return Err(anyhow::anyhow!("DNS message is a query, not a response"));
+    }
+    let mut response = DnsResponse {
+        id: field(0),
+        rcode: (flags & 0xf) as u8,
+        truncated: flags & FLAG_TC != 0,
+        addrs: vec![],
+    };
+
+    let mut pos = 12;
+    for _ in 0..field(4) {
+        pos = skip_name(msg, pos)? + 4; // type and class
+    }
+    for _ in 0..field(6) {
+        pos = skip_name(msg, pos)?;
+        let record = msg
+            .get(pos..pos + 10)
+            .ok_or_else(|| anyhow::anyhow!("DNS record cut short"))?;
+        let rtype = u16::from_be_bytes([record[0], record[1]]);
+        let len = u16::from_be_bytes([record[8], record[9]]) as usize;
+        pos += 10;
+        let data = msg
+            .get(pos..pos + len)
+            .ok_or_else(|| anyhow::anyhow!("DNS record data cut short"))?;
+        match (rtype, len) {
+            (TYPE_A, 4) => {
+                let octets: [u8; 4] = data.try_into()?;
+                response.addrs.push(IpAddr::V4(Ipv4Addr::from(octets)));
+            }
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = data.try_into()?;
+                response.addrs.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {} // CNAME and anything else
+        }
+        pos += len;
+    }
+    Ok(response)
+}
+
+/// Position right after the name at `pos`. A pointer (two top bits set) ends
+/// the name, it points at the rest of it somewhere earlier in the message.
+fn skip_name(msg: &[u8], mut pos: usize) -> Result<usize, anyhow::Error> {
+    loop {
+        let len = *msg
+            .get(pos)
+            .ok_or_else(|| anyhow::anyhow!("DNS name cut short"))? as usize;
+        match len {
+            0 => return Ok(pos + 1),
+            len if len & 0xc0 == 0xc0 => return Ok(pos + 2),
+            len => pos += 1 + len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A response to `query(0x1234, "www.example.com", TYPE_A)` with `answers`
+    /// as (name, type, data), the name already encoded
+    fn response(rcode: u16, answers: &[(&[u8], u16, &[u8])]) -> Vec<u8> {
+        let mut msg = query(0x1234, "www.example.com", TYPE_A).unwrap();
+        msg[2..4].copy_from_slice(&(FLAG_QR | FLAGS_RD | 0x0080 | rcode).to_be_bytes());
+        msg[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (name, rtype, data) in answers {
+            msg.extend_from_slice(name);
+            msg.extend_from_slice(&rtype.to_be_bytes());
+            msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+            msg.extend_from_slice(&300u32.to_be_bytes());
+            msg.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            msg.extend_from_slice(data);
+        }
+        msg
+    }
+
+    /// Points at the question's name, right after the header
+    const QUESTION_NAME: &[u8] = &[0xc0, 12];
+
+    #[test]
+    fn query_encodes_labels() {
+        let msg = query(7, "www.example.com.", TYPE_AAAA).unwrap();
+        assert_eq!(&msg[..2], &[0, 7]);
+        assert_eq!(&msg[12..29], b"\x03www\x07example\x03com\x00");
+        assert_eq!(&msg[29..], &[0, 28, 0, 1]);
+    }
+
+    #[test]
+    fn query_rejects_empty_label() {
+        assert!(query(1, "www..example.com", TYPE_A).is_err());
+        assert!(query(1, ".example.com", TYPE_A).is_err());
+        assert!(query(1, "", TYPE_A).is_err());
+    }
+
+    #[test]
+    fn query_rejects_label_over_63_bytes() {
+        let longest = format!("{}.com", "a".repeat(63));
+        assert!(query(1, &longest, TYPE_A).is_ok());
+        let too_long = format!("{}.com", "a".repeat(64));
+        assert!(query(1, &too_long, TYPE_A).is_err());
+    }
+
+    #[test]
+    fn parses_answer_with_compressed_name() {
+        let msg = response(0, &[(QUESTION_NAME, TYPE_A, &[192, 0, 2, 1])]);
+        let response = parse_response(&msg).unwrap();
+        assert_eq!(response.id, 0x1234);
+        assert_eq!(response.rcode, 0);
+        assert!(!response.truncated);
+        assert_eq!(response.addrs, vec![IpAddr::from([192, 0, 2, 1])]);
+    }
+
+    #[test]
+    fn skips_cname_before_addresses() {
+        // www.example.com is web.example.com, the target ends in a pointer to example.com
+        let cname_target: &[u8] = &[3, b'w', b'e', b'b', 0xc0, 16];
+        let target_offset = response(0, &[]).len() + 2 + 10;
+        let target_name: &[u8] = &[0xc0, target_offset as u8];
+        let v6 = Ipv6Addr::LOCALHOST.octets();
+        let msg = response(
+            0,
+            &[
+                (QUESTION_NAME, 5, cname_target),
+                (target_name, TYPE_A, &[192, 0, 2, 7]),
+                (target_name, TYPE_AAAA, &v6),
+            ],
+        );
+        let response = parse_response(&msg).unwrap();
+        assert_eq!(
+            response.addrs,
+            vec![
+                IpAddr::from([192, 0, 2, 7]),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_rcode_and_truncated_flag() {
+        let mut msg = response(RCODE_NXDOMAIN as u16, &[]);
+        assert_eq!(parse_response(&msg).unwrap().rcode, RCODE_NXDOMAIN);
+        msg[2] |= (FLAG_TC >> 8) as u8;
+        assert!(parse_response(&msg).unwrap().truncated);
+    }
+
+    #[test]
+    fn rejects_truncated_record() {
+        let msg = response(0, &[(QUESTION_NAME, TYPE_A, &[192, 0, 2, 1])]);
+        // in the record data, in the fixed fields, and in the name
+        for len in [msg.len() - 1, msg.len() - 8, msg.len() - 15] {
+            assert!(parse_response(&msg[..len]).is_err(), "cut at {}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_short_header_and_queries() {
+        assert!(parse_response(&[0; 11]).is_err());
+        let query = query(1, "example.com", TYPE_A).unwrap();
+        assert!(parse_response(&query).is_err());
+    }
+}
@@ -1,37 +1,51 @@
-use super::libc::epoll_event;
-use crate::sys_libc::{EpollFd, SocketFd, epoll_event::EpollEvent};
+use super::libc::{epoll_event, sigset_t, timespec};
+use super::retry::restartable_with_timeout;
+use crate::sys_libc::{EpollFd, Errno, SigSet, SocketRef, SysError, epoll_event::EpollEvent};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-pub fn epoll_create1(flags: i32) -> Result<EpollFd, anyhow::Error> {
-    let epoll_fd = unsafe { super::libc::epoll_create1(flags) };
+/// Set after the first ENOSYS, no point asking an old kernel every time
+static NO_EPOLL_PWAIT2: AtomicBool = AtomicBool::new(false);
+
+pub fn epoll_create1(flags: i32) -> Result<EpollFd, SysError> {
+    let epoll_fd = unsafe { syscall!(epoll_create1(flags)) };
     if epoll_fd == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("epoll_create1 failed: {}", errno));
+        return Err(SysError::last_os_error("epoll_create1"));
     }
     Ok(EpollFd(epoll_fd))
 }
 
-pub fn epoll_ctl(
+pub fn epoll_ctl<'a>(
     epoll_fd: &EpollFd,
     op: i32,
-    fd: &SocketFd,
+    fd: impl Into<SocketRef<'a>>,
     event: &EpollEvent,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), SysError> {
+    let fd = fd.into();
     let event = &event.0 as *const epoll_event as *mut epoll_event;
-    let result = unsafe { super::libc::epoll_ctl(epoll_fd.0, op, fd.0, event) };
+    let result = unsafe { syscall!(epoll_ctl(epoll_fd.0, op, fd.0, event)) };
     if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("epoll_ctl failed: {}", errno));
+        return Err(SysError::last_os_error("epoll_ctl"));
     }
     Ok(())
 }
 
-pub fn epoll_ctl_remove(epoll_fd: &EpollFd, fd: &SocketFd) -> Result<(), anyhow::Error> {
+pub fn epoll_ctl_remove<'a>(
+    epoll_fd: &EpollFd,
+    fd: impl Into<SocketRef<'a>>,
+) -> Result<(), SysError> {
+    let fd = fd.into();
     let event = std::ptr::null_mut();
-    let result =
-        unsafe { super::libc::epoll_ctl(epoll_fd.0, super::libc::EPOLL_CTL_DEL, fd.0, event) };
+    let result = unsafe {
+        syscall!(epoll_ctl(
+            epoll_fd.0,
+            super::libc::EPOLL_CTL_DEL,
+            fd.0,
+            event
+        ))
+    };
     if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("epoll_ctl remove failed: {}", errno));
+        return Err(SysError::last_os_error("epoll_ctl"));
     }
     Ok(())
 }
@@ -40,13 +54,95 @@ pub fn epoll_wait(
     epoll_fd: &EpollFd,
     events: &mut [epoll_event],
     timeout: i32,
-) -> Result<i32, anyhow::Error> {
+) -> Result<i32, SysError> {
     let maxevents = events.len() as i32;
-    let result =
-        unsafe { super::libc::epoll_wait(epoll_fd.0, events.as_mut_ptr(), maxevents, timeout) };
+    restartable_with_timeout(timeout, |timeout| {
+        let result = unsafe {
+            syscall!(epoll_wait(
+                epoll_fd.0,
+                events.as_mut_ptr(),
+                maxevents,
+                timeout
+            ))
+        };
+        super::stats::count_wakeup();
+        if result == -1 {
+            return Err(SysError::last_os_error("epoll_wait"));
+        }
+        Ok(result)
+    })
+}
+
+/// `epoll_wait` with the signal mask swapped in for the duration of the wait,
+/// see `pselect`. Timeouts are rounded up to whole milliseconds. Never restarted.
+pub fn epoll_pwait(
+    epoll_fd: &EpollFd,
+    events: &mut [epoll_event],
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> Result<i32, SysError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32,
+        None => -1,
+    };
+    let result = unsafe {
+        syscall!(epoll_pwait(
+            epoll_fd.0,
+            events.as_mut_ptr(),
+            events.len() as i32,
+            timeout,
+            mask_ptr(sigmask)
+        ))
+    };
+    super::stats::count_wakeup();
     if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("epoll_wait failed: {}", errno));
+        return Err(SysError::last_os_error("epoll_pwait"));
     }
     Ok(result)
 }
+
+/// `epoll_pwait` with a nanosecond timeout. Kernels before 5.11 don't have it,
+/// there we fall back to `epoll_pwait` and lose the sub-millisecond part.
+pub fn epoll_pwait2(
+    epoll_fd: &EpollFd,
+    events: &mut [epoll_event],
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> Result<i32, SysError> {
+    if NO_EPOLL_PWAIT2.load(Ordering::Relaxed) {
+        return epoll_pwait(epoll_fd, events, timeout, sigmask);
+    }
+    let ts = timeout.map(timespec::from);
+    let ts_ptr = match ts.as_ref() {
+        Some(ts) => ts as *const timespec,
+        None => std::ptr::null(),
+    };
+    let result = unsafe {
+        syscall!(epoll_pwait2(
+            epoll_fd.0,
+            events.as_mut_ptr(),
+            events.len() as i32,
+            ts_ptr,
+            mask_ptr(sigmask)
+        ))
+    };
+    if result == -1 {
+        let err = SysError::last_os_error("epoll_pwait2");
+        if err.kind == Errno::ENOSYS {
+            crate::cyan!("{}, falling back to epoll_pwait", err);
+            NO_EPOLL_PWAIT2.store(true, Ordering::Relaxed);
+            return epoll_pwait(epoll_fd, events, timeout, sigmask);
+        }
+        super::stats::count_wakeup();
+        return Err(err);
+    }
+    super::stats::count_wakeup();
+    Ok(result)
+}
+
+fn mask_ptr(sigmask: Option<&SigSet>) -> *const sigset_t {
+    match sigmask {
+        Some(sigmask) => sigmask.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
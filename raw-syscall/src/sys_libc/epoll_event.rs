@@ -1,15 +1,16 @@
 use crate::sys_libc::libc::epoll_data_t;
 
-use super::{SocketFd, libc::epoll_event};
+use super::{SocketRef, libc::epoll_event};
 use std::marker::PhantomData;
 
-/// Ties the lifetime of the epoll_event to the lifetime of the SocketFd
+/// Ties the lifetime of the epoll_event to the lifetime of the socket
 /// while maintaining the same memory layout as epoll_event
 #[repr(transparent)]
-pub struct EpollEvent<'a>(pub(crate) epoll_event, PhantomData<&'a SocketFd>);
+pub struct EpollEvent<'a>(pub(crate) epoll_event, PhantomData<SocketRef<'a>>);
 
 impl<'a> EpollEvent<'a> {
-    pub fn new(sockfd: &'a SocketFd, events: u32) -> Self {
+    pub fn new(sockfd: impl Into<SocketRef<'a>>, events: u32) -> Self {
+        let sockfd = sockfd.into();
         EpollEvent(
             epoll_event {
                 events,
@@ -21,8 +22,9 @@ impl<'a> EpollEvent<'a> {
         )
     }
 
-    // SAFETY: The caller must ensure that the output fd will not outlive the
-    // underlining SocketFd
+    /// # Safety
+    /// The caller must ensure that the output fd will not outlive the
+    /// underlining socket
     pub unsafe fn fd(&self) -> i32 {
         unsafe { self.0.data.u64 as i32 }
     }
@@ -1,10 +1,6 @@
 //! Epoll file descriptor wrapper.
 //! Closes the epoll instance when dropped.
 
-use super::libc;
-use crate::cyan;
-use std::fmt::Display;
-
 pub struct EpollFd(pub(crate) i32);
 
 impl PartialEq for EpollFd {
@@ -13,15 +9,4 @@ impl PartialEq for EpollFd {
     }
 }
 
-impl Drop for EpollFd {
-    fn drop(&mut self) {
-        unsafe { libc::close(self.0) };
-        cyan!("Epoll instance closed {}", self);
-    }
-}
-
-impl Display for EpollFd {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "EpollFd({})", self.0)
-    }
-}
+owned_fd_type!(EpollFd, "an open epoll instance", "Epoll instance closed");
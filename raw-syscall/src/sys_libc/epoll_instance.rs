@@ -0,0 +1,109 @@
+//! Typed epoll: registrations carry an `Interest` and a `u64` token
+//! that comes back untouched with every event, usually an index into
+//! the caller's own table of sockets.
+use super::libc::{self, EPOLL_CLOEXEC, epoll_data_t, epoll_event};
+use super::{
+    EpollFd, Events, Interest, SigSet, SocketRef, SysError, epoll_create1, epoll_pwait2, epoll_wait,
+};
+use crate::cyan;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::time::Duration;
+
+pub struct Epoll {
+    fd: EpollFd,
+}
+
+impl Epoll {
+    pub fn new() -> Result<Self, SysError> {
+        let fd = epoll_create1(EPOLL_CLOEXEC)?;
+        cyan!("Created epoll instance {}", fd);
+        Ok(Epoll { fd })
+    }
+
+    /// Starts watching `fd`. Closing the fd removes it, or call `delete` first.
+    pub fn add<'a>(
+        &self,
+        fd: impl Into<SocketRef<'a>>,
+        token: u64,
+        interest: Interest,
+    ) -> Result<(), SysError> {
+        self.ctl(libc::EPOLL_CTL_ADD, fd.into(), token, interest)
+    }
+
+    /// Replaces both the interest and the token, also re-arms ONESHOT registrations
+    pub fn modify<'a>(
+        &self,
+        fd: impl Into<SocketRef<'a>>,
+        token: u64,
+        interest: Interest,
+    ) -> Result<(), SysError> {
+        self.ctl(libc::EPOLL_CTL_MOD, fd.into(), token, interest)
+    }
+
+    pub fn delete<'a>(&self, fd: impl Into<SocketRef<'a>>) -> Result<(), SysError> {
+        let fd = fd.into();
+        let result = unsafe {
+            syscall!(epoll_ctl(
+                self.fd.0,
+                libc::EPOLL_CTL_DEL,
+                fd.0,
+                std::ptr::null_mut()
+            ))
+        };
+        if result == -1 {
+            return Err(SysError::last_os_error("epoll_ctl"));
+        }
+        Ok(())
+    }
+
+    fn ctl(&self, op: i32, fd: SocketRef, token: u64, interest: Interest) -> Result<(), SysError> {
+        let mut event = epoll_event {
+            events: interest.bits(),
+            data: epoll_data_t { u64: token },
+        };
+        let result = unsafe { syscall!(epoll_ctl(self.fd.0, op, fd.0, &mut event)) };
+        if result == -1 {
+            return Err(SysError::last_os_error("epoll_ctl"));
+        }
+        Ok(())
+    }
+
+    /// Fills `events` and returns how many fds are ready, 0 on timeout.
+    /// None waits forever, timeouts are rounded up to whole milliseconds.
+    pub fn wait(&self, events: &mut Events, timeout: Option<Duration>) -> Result<usize, SysError> {
+        let timeout = match timeout {
+            Some(timeout) => timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        events.clear();
+        let ready = epoll_wait(&self.fd, events.as_mut_slice(), timeout)? as usize;
+        events.set_len(ready);
+        Ok(ready)
+    }
+
+    /// `wait` without the millisecond rounding (`epoll_pwait2`), and with
+    /// `sigmask` in place only while blocked, see `pselect`. Never restarted.
+    pub fn pwait(
+        &self,
+        events: &mut Events,
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> Result<usize, SysError> {
+        events.clear();
+        let ready = epoll_pwait2(&self.fd, events.as_mut_slice(), timeout, sigmask)? as usize;
+        events.set_len(ready);
+        Ok(ready)
+    }
+}
+
+impl AsFd for Epoll {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for Epoll {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
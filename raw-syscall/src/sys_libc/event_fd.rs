@@ -0,0 +1,37 @@
+//! Eventfd wrapper: a 64 bit counter in the kernel that is readable while not zero.
+//! Register it with epoll next to the sockets and any thread can wake up the loop
+//! by bumping the counter. Closes the fd when dropped.
+use super::libc::{EFD_CLOEXEC, EFD_NONBLOCK};
+use super::{SysError, eventfd, read, write};
+use crate::cyan;
+
+pub struct EventFd(pub(crate) i32);
+
+impl EventFd {
+    /// Non-blocking, so `drain` never parks the event loop
+    pub fn new() -> Result<Self, SysError> {
+        let fd = eventfd(0, EFD_NONBLOCK | EFD_CLOEXEC)?;
+        cyan!("Created eventfd {}", fd);
+        Ok(fd)
+    }
+
+    /// Adds one to the counter and wakes up whoever waits on it. Safe from any thread,
+    /// notifying several times before the loop gets to run only wakes it once.
+    pub fn notify(&self) -> Result<(), SysError> {
+        // None means the counter is at its max, the fd is readable already
+        write(self, &1u64.to_ne_bytes())?;
+        Ok(())
+    }
+
+    /// Resets the counter, returns how many notifications it held (0 for none).
+    /// Level triggered epoll keeps reporting the fd until this is called.
+    pub fn drain(&self) -> Result<u64, SysError> {
+        let mut buf = [0u8; 8];
+        match read(self, &mut buf)? {
+            Some(_) => Ok(u64::from_ne_bytes(buf)),
+            None => Ok(0),
+        }
+    }
+}
+
+owned_fd_type!(EventFd, "an open eventfd", "Eventfd closed");
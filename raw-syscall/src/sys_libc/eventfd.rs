@@ -0,0 +1,10 @@
+use super::{EventFd, SysError};
+
+/// `flags` is any of `EFD_NONBLOCK`, `EFD_CLOEXEC` and `EFD_SEMAPHORE`
+pub fn eventfd(initval: u32, flags: i32) -> Result<EventFd, SysError> {
+    let fd = unsafe { syscall!(eventfd(initval, flags)) };
+    if fd == -1 {
+        return Err(SysError::last_os_error("eventfd"));
+    }
+    Ok(EventFd(fd))
+}
@@ -0,0 +1,57 @@
+//! Buffer `Epoll::wait` fills in.
+//! Reads the packed `epoll_event`s by value so callers never touch the union.
+use super::Readiness;
+use super::libc::epoll_event;
+
+pub struct Events {
+    buf: Vec<epoll_event>,
+    /// how many entries the last wait filled in
+    len: usize,
+}
+
+impl Events {
+    /// At most `capacity` events are returned per wait, the rest wait for the next call.
+    /// Panics on 0, `epoll_wait` fails every call with EINVAL without room for one.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "Events need a capacity of at least 1");
+        let mut buf = Vec::with_capacity(capacity);
+        // all zeroes is a valid epoll_event
+        buf.resize_with(capacity, || unsafe { std::mem::zeroed() });
+        Events { buf, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// `(token, readiness)` for every event of the last wait
+    pub fn iter(&self) -> impl Iterator<Item = (u64, Readiness)> + '_ {
+        self.buf[..self.len].iter().map(|event| {
+            // copies out of the packed struct, no references to unaligned fields
+            let events = event.events;
+            // SAFETY: Epoll only ever registers u64 tokens
+            let token = unsafe { event.data.u64 };
+            (token, Readiness(events))
+        })
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [epoll_event] {
+        &mut self.buf
+    }
+
+    pub(crate) fn set_len(&mut self, len: usize) {
+        self.len = len.min(self.buf.len());
+    }
+}
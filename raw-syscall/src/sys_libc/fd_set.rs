@@ -1,12 +1,14 @@
-use super::SocketFd;
 use super::libc::fd_set;
+use super::{Errno, SocketRef, SysError};
 use std::fmt::Display;
-use std::usize;
 
-/// Fdset is a kind of bloom filter for file descriptors
-/// If a bit is not set, the fd is definitely not in the set
-/// If a bit is set, it might have been set by that fd or by another fd that hashes to the same bit
-/// If too many fds are open there can be an out of bounds access??? (realy???)
+/// glibc's FD_SETSIZE, the number of fds a plain `fd_set` can hold
+pub const FD_SETSIZE: usize = 1024;
+
+/// Fdset is a bitmap of file descriptors: bit n is fd n.
+/// It has room for FD_SETSIZE fds; `set` refuses anything bigger instead of
+/// writing out of bounds (what FD_SET does in C), use `DynFdSet` for those.
+#[derive(Default)]
 pub struct FdSet {
     fds: [u64; 16], // 1024 bits / 64 = 16 u64s
 }
@@ -20,44 +22,147 @@ impl FdSet {
         self.fds.fill(0);
     }
 
-    pub fn set(&mut self, fd: &SocketFd) {
-        let (idx, bit) = Self::idx_bit(fd);
-        if idx < self.fds.len() {
-            self.fds[idx] |= bit;
+    /// Fails with EINVAL for fds >= FD_SETSIZE
+    pub fn set<'a>(&mut self, fd: impl Into<SocketRef<'a>>) -> Result<(), SysError> {
+        let (idx, bit) = idx_bit(fd.into());
+        match self.fds.get_mut(idx) {
+            Some(word) => {
+                *word |= bit;
+                Ok(())
+            }
+            None => Err(SysError::new("FdSet::set", Errno::EINVAL.raw())),
+        }
+    }
+
+    pub fn is_set<'a>(&self, fd: impl Into<SocketRef<'a>>) -> bool {
+        is_set(&self.fds, fd.into())
+    }
+
+    /// fds in the set, lowest first
+    pub fn iter(&self) -> FdSetIter<'_> {
+        FdSetIter::new(&self.fds)
+    }
+}
+
+/// An fd set that grows to fit any fd. The kernel accepts bitmaps of any size
+/// as long as `nfds` doesn't go past them, glibc documents this for `select`.
+#[derive(Default)]
+pub struct DynFdSet {
+    fds: Vec<u64>,
+}
+
+impl DynFdSet {
+    pub fn new() -> Self {
+        Self { fds: Vec::new() }
+    }
+
+    /// Keeps the allocation for the next round
+    pub fn clear(&mut self) {
+        self.fds.fill(0);
+    }
+
+    pub fn set<'a>(&mut self, fd: impl Into<SocketRef<'a>>) {
+        let (idx, bit) = idx_bit(fd.into());
+        if idx >= self.fds.len() {
+            self.fds.resize(idx + 1, 0);
         }
+        self.fds[idx] |= bit;
     }
 
-    pub fn is_set(&self, fd: &SocketFd) -> bool {
-        let (idx, bit) = Self::idx_bit(fd);
-        if idx < self.fds.len() {
-            (self.fds[idx] & bit) != 0
-        } else {
-            false
+    pub fn is_set<'a>(&self, fd: impl Into<SocketRef<'a>>) -> bool {
+        is_set(&self.fds, fd.into())
+    }
+
+    pub fn iter(&self) -> FdSetIter<'_> {
+        FdSetIter::new(&self.fds)
+    }
+}
+
+/// What `select` needs from a set: a bitmap covering the first `nfds` fds
+pub trait SelectSet {
+    /// Makes sure the kernel can read and write `nfds` bits
+    fn reserve(&mut self, nfds: i32) -> Result<(), SysError>;
+    fn as_mut_ptr(&mut self) -> *mut fd_set;
+}
+
+impl SelectSet for FdSet {
+    fn reserve(&mut self, nfds: i32) -> Result<(), SysError> {
+        if nfds as usize > FD_SETSIZE {
+            return Err(SysError::new("select", Errno::EINVAL.raw()));
         }
+        Ok(())
     }
 
-    fn idx_bit(fd: &SocketFd) -> (usize, u64) {
-        let fd = fd.0 as usize;
-        let idx = fd / 64;
-        let bit = 1u64 << (fd % 64);
-        (idx, bit)
+    fn as_mut_ptr(&mut self) -> *mut fd_set {
+        self.fds.as_mut_ptr() as *mut fd_set
     }
+}
 
-    pub(crate) fn as_mut_ptr(&mut self) -> *mut fd_set {
+impl SelectSet for DynFdSet {
+    fn reserve(&mut self, nfds: i32) -> Result<(), SysError> {
+        let words = (nfds.max(0) as usize).div_ceil(64);
+        if words > self.fds.len() {
+            self.fds.resize(words, 0);
+        }
+        Ok(())
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut fd_set {
         self.fds.as_mut_ptr() as *mut fd_set
     }
 }
 
+fn idx_bit(fd: SocketRef) -> (usize, u64) {
+    let fd = fd.0 as usize;
+    let idx = fd / 64;
+    let bit = 1u64 << (fd % 64);
+    (idx, bit)
+}
+
+fn is_set(fds: &[u64], fd: SocketRef) -> bool {
+    let (idx, bit) = idx_bit(fd);
+    fds.get(idx).is_some_and(|word| word & bit != 0)
+}
+
+/// Walks the set bits only, skipping empty words
+pub struct FdSetIter<'a> {
+    words: &'a [u64],
+    idx: usize,
+    current: u64,
+}
+
+impl<'a> FdSetIter<'a> {
+    fn new(words: &'a [u64]) -> Self {
+        FdSetIter {
+            words,
+            idx: 0,
+            current: words.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl Iterator for FdSetIter<'_> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        while self.current == 0 {
+            self.idx += 1;
+            self.current = *self.words.get(self.idx)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1; // clear the lowest set bit
+        Some((self.idx * 64 + bit) as i32)
+    }
+}
+
 impl Display for FdSet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut bits = Vec::new();
-        for (i, &chunk) in self.fds.iter().enumerate() {
-            for j in 0..64 {
-                if (chunk & (1u64 << j)) != 0 {
-                    bits.push(i * 64 + j);
-                }
-            }
-        }
-        write!(f, "FdSet({:?})", bits)
+        write!(f, "FdSet({:?})", self.iter().collect::<Vec<_>>())
+    }
+}
+
+impl Display for DynFdSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DynFdSet({:?})", self.iter().collect::<Vec<_>>())
     }
 }
@@ -1,13 +1,12 @@
-use super::SocketFd;
 use super::libc;
+use super::{SocketFd, SysError};
 use std::mem;
 
-pub fn get_socket_error(sockfd: &SocketFd) -> Result<i32, anyhow::Error> {
-    let mut error: i32 = 0;
-    let mut len = mem::size_of::<i32>() as u32;
-    let mut optval = error.to_ne_bytes();
+/// Pending error on the socket (0 if none), e.g. the outcome of a non-blocking connect
+pub fn get_socket_error(sockfd: &SocketFd) -> Result<i32, SysError> {
+    let mut optval = [0u8; mem::size_of::<i32>()];
     let _ = getsockopt(sockfd, libc::SOL_SOCKET, libc::SO_ERROR, &mut optval)?;
-    Ok(error)
+    Ok(i32::from_ne_bytes(optval))
 }
 
 pub fn getsockopt(
@@ -15,13 +14,19 @@ pub fn getsockopt(
     level: i32,
     optname: i32,
     optval: &mut [u8],
-) -> Result<usize, anyhow::Error> {
+) -> Result<usize, SysError> {
     let mut optlen = optval.len() as u32;
-    let ret =
-        unsafe { libc::getsockopt(sockfd.0, level, optname, optval.as_mut_ptr(), &mut optlen) };
+    let ret = unsafe {
+        syscall!(getsockopt(
+            sockfd.0,
+            level,
+            optname,
+            optval.as_mut_ptr(),
+            &mut optlen
+        ))
+    };
     if ret == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("Failed to get socket option: {}", errno));
+        return Err(SysError::last_os_error("getsockopt"));
     }
     Ok(optlen as usize)
 }
@@ -0,0 +1,91 @@
+//! `/etc/hosts` lookups, checked before any DNS query like glibc does with
+//! `hosts: files dns` in nsswitch.conf.
+use std::net::IpAddr;
+use std::path::Path;
+
+pub const HOSTS_PATH: &str = "/etc/hosts";
+
+/// Every address listed for `name`, as a canonical name or an alias.
+/// A missing file is the same as an empty one.
+pub fn lookup_hosts(path: impl AsRef<Path>, name: &str) -> Result<Vec<IpAddr>, anyhow::Error> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(parse_hosts(&text, name))
+}
+
+/// `address name [aliases...]` per line, `#` starts a comment
+fn parse_hosts(text: &str, name: &str) -> Vec<IpAddr> {
+    let name = name.trim_end_matches('.');
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            let addr = fields.next()?.parse().ok()?;
+            fields
+                .any(|host| host.eq_ignore_ascii_case(name))
+                .then_some(addr)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "\
+# The usual
+127.0.0.1   localhost
+::1         localhost ip6-localhost ip6-loopback
+
+192.0.2.10  web.example.com   web   www # the alias www too
+#192.0.2.11 old.example.com
+192.0.2.12  Db.Example.com
+not-an-ip   broken.example.com
+";
+
+    fn addrs(list: &[&str]) -> Vec<IpAddr> {
+        list.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn finds_canonical_names_and_aliases() {
+        assert_eq!(
+            parse_hosts(HOSTS, "web.example.com"),
+            addrs(&["192.0.2.10"])
+        );
+        assert_eq!(parse_hosts(HOSTS, "web"), addrs(&["192.0.2.10"]));
+        assert_eq!(parse_hosts(HOSTS, "ip6-loopback"), addrs(&["::1"]));
+    }
+
+    #[test]
+    fn collects_every_line_for_a_name() {
+        assert_eq!(
+            parse_hosts(HOSTS, "localhost"),
+            addrs(&["127.0.0.1", "::1"])
+        );
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(parse_hosts(HOSTS, "www"), addrs(&["192.0.2.10"]));
+        assert!(parse_hosts(HOSTS, "old.example.com").is_empty());
+        assert!(parse_hosts(HOSTS, "the").is_empty());
+        assert!(parse_hosts(HOSTS, "#192.0.2.11").is_empty());
+    }
+
+    #[test]
+    fn ignores_case_and_trailing_dot() {
+        assert_eq!(
+            parse_hosts(HOSTS, "db.example.com."),
+            addrs(&["192.0.2.12"])
+        );
+    }
+
+    #[test]
+    fn skips_lines_without_an_address() {
+        assert!(parse_hosts(HOSTS, "broken.example.com").is_empty());
+    }
+}
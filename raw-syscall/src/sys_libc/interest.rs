@@ -0,0 +1,131 @@
+//! Typed epoll masks: `Interest` is what we ask for, `Readiness` what we get back.
+//! Hand rolled bitflags over the `EPOLL*` constants.
+use super::libc;
+use std::fmt::Display;
+use std::ops::{BitOr, BitOrAssign};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Interest(u32);
+
+impl Interest {
+    pub const READABLE: Interest = Interest(libc::EPOLLIN);
+    pub const WRITABLE: Interest = Interest(libc::EPOLLOUT);
+    /// Out of band data
+    pub const PRIORITY: Interest = Interest(libc::EPOLLPRI);
+    /// The peer shut down its writing half (read will return 0)
+    pub const READ_CLOSED: Interest = Interest(libc::EPOLLRDHUP);
+    /// Report only changes in readiness, not the readiness itself
+    pub const EDGE: Interest = Interest(libc::EPOLLET);
+    /// Disable the fd after one event, re-arm it with `Epoll::modify`
+    pub const ONESHOT: Interest = Interest(libc::EPOLLONESHOT);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, other: Interest) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Interest {
+    type Output = Interest;
+
+    fn bitor(self, rhs: Interest) -> Interest {
+        Interest(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Interest {
+    fn bitor_assign(&mut self, rhs: Interest) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Display for Interest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_flags(
+            f,
+            self.0,
+            &[
+                (libc::EPOLLIN, "READABLE"),
+                (libc::EPOLLOUT, "WRITABLE"),
+                (libc::EPOLLPRI, "PRIORITY"),
+                (libc::EPOLLRDHUP, "READ_CLOSED"),
+                (libc::EPOLLET, "EDGE"),
+                (libc::EPOLLONESHOT, "ONESHOT"),
+            ],
+        )
+    }
+}
+
+/// The events the kernel reported for one fd
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Readiness(pub(crate) u32);
+
+impl Readiness {
+    pub fn is_readable(self) -> bool {
+        self.0 & libc::EPOLLIN != 0
+    }
+
+    pub fn is_writable(self) -> bool {
+        self.0 & libc::EPOLLOUT != 0
+    }
+
+    pub fn is_priority(self) -> bool {
+        self.0 & libc::EPOLLPRI != 0
+    }
+
+    /// EPOLLERR, reported even if not asked for. `get_socket_error` tells which one
+    pub fn is_error(self) -> bool {
+        self.0 & libc::EPOLLERR != 0
+    }
+
+    /// EPOLLHUP, both directions are closed. Reported even if not asked for
+    pub fn is_hup(self) -> bool {
+        self.0 & libc::EPOLLHUP != 0
+    }
+
+    pub fn is_read_closed(self) -> bool {
+        self.0 & libc::EPOLLRDHUP != 0
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl Display for Readiness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_flags(
+            f,
+            self.0,
+            &[
+                (libc::EPOLLIN, "READABLE"),
+                (libc::EPOLLOUT, "WRITABLE"),
+                (libc::EPOLLPRI, "PRIORITY"),
+                (libc::EPOLLERR, "ERROR"),
+                (libc::EPOLLHUP, "HUP"),
+                (libc::EPOLLRDHUP, "READ_CLOSED"),
+            ],
+        )
+    }
+}
+
+/// Writes the names of the set flags as `A | B`
+fn write_flags(
+    f: &mut std::fmt::Formatter<'_>,
+    bits: u32,
+    names: &[(u32, &str)],
+) -> std::fmt::Result {
+    let set: Vec<&str> = names
+        .iter()
+        .filter(|(flag, _)| bits & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        write!(f, "(empty)")
+    } else {
+        write!(f, "{}", set.join(" | "))
+    }
+}
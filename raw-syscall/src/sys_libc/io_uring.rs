@@ -0,0 +1,45 @@
+use super::libc::{IORING_ENTER_GETEVENTS, io_uring_params};
+use super::{IoUringFd, SigSet, SysError};
+
+/// Creates a ring with room for `entries` submissions (rounded up to a power of 2).
+/// The kernel fills in `params` with the sizes and offsets needed to `mmap` it.
+pub fn io_uring_setup(entries: u32, params: &mut io_uring_params) -> Result<IoUringFd, SysError> {
+    let fd = unsafe { syscall!(io_uring_setup(entries, params)) };
+    if fd == -1 {
+        return Err(SysError::last_os_error("io_uring_setup"));
+    }
+    Ok(IoUringFd(fd))
+}
+
+/// Hands the kernel `to_submit` new entries from the submission ring and,
+/// with `IORING_ENTER_GETEVENTS` in `flags`, waits for `min_complete`
+/// completions. `sigmask` is in place while waiting, see `pselect`.
+/// Returns how many entries were consumed. Never restarted.
+pub fn io_uring_enter(
+    fd: &IoUringFd,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sigmask: Option<&SigSet>,
+) -> Result<u32, SysError> {
+    let sigmask = match sigmask {
+        Some(sigmask) => sigmask.as_ptr(),
+        None => std::ptr::null(),
+    };
+    let result = unsafe {
+        syscall!(io_uring_enter(
+            fd.0,
+            to_submit,
+            min_complete,
+            flags,
+            sigmask
+        ))
+    };
+    if flags & IORING_ENTER_GETEVENTS != 0 {
+        super::stats::count_wakeup();
+    }
+    if result == -1 {
+        return Err(SysError::last_os_error("io_uring_enter"));
+    }
+    Ok(result as u32)
+}
@@ -0,0 +1,114 @@
+//! What goes into an io_uring and what comes back out.
+//! An `Sqe` only keeps raw pointers to the buffers and addresses it was built
+//! from, the kernel reads and writes them whenever it gets to the operation.
+//! They have to stay put until the completion shows up, which is why
+//! `IoUring::push` is unsafe.
+use super::libc::{
+    IORING_OP_ASYNC_CANCEL, IORING_OP_CONNECT, IORING_OP_POLL_ADD, IORING_OP_RECV, IORING_OP_SEND,
+    IORING_OP_TIMEOUT, POLLIN, io_uring_cqe, io_uring_sqe, timespec,
+};
+use super::{SockAddr, SocketRef, SysError};
+use std::fmt::Display;
+
+#[derive(Clone, Copy)]
+pub struct Sqe(pub(crate) io_uring_sqe);
+
+impl Sqe {
+    fn new(opcode: u8, fd: i32) -> Self {
+        Sqe(io_uring_sqe {
+            opcode,
+            fd,
+            ..Default::default()
+        })
+    }
+
+    /// `connect(2)`, the socket doesn't have to be non-blocking
+    pub fn connect<'a>(fd: impl Into<SocketRef<'a>>, addr: &SockAddr) -> Self {
+        let mut sqe = Self::new(IORING_OP_CONNECT, fd.into().0);
+        sqe.0.addr = addr.as_ptr() as u64;
+        // the address length goes where other ops have their file offset
+        sqe.0.off = addr.len() as u64;
+        sqe
+    }
+
+    /// `send(2)`, completes with the number of bytes sent
+    pub fn send<'a>(fd: impl Into<SocketRef<'a>>, buf: &[u8]) -> Self {
+        let mut sqe = Self::new(IORING_OP_SEND, fd.into().0);
+        sqe.0.addr = buf.as_ptr() as u64;
+        sqe.0.len = buf.len() as u32;
+        sqe
+    }
+
+    /// `recv(2)`, completes with the number of bytes received, 0 once the peer closed
+    pub fn recv<'a>(fd: impl Into<SocketRef<'a>>, buf: &mut [u8]) -> Self {
+        let mut sqe = Self::new(IORING_OP_RECV, fd.into().0);
+        sqe.0.addr = buf.as_mut_ptr() as u64;
+        sqe.0.len = buf.len() as u32;
+        sqe
+    }
+
+    /// Completes with ETIME once `timeout` has passed, or with 0 as soon as
+    /// `count` other operations completed (0 for only the time).
+    /// Not tied to any fd: the ring's own timer.
+    pub fn timeout(timeout: &timespec, count: u32) -> Self {
+        let mut sqe = Self::new(IORING_OP_TIMEOUT, -1);
+        sqe.0.addr = timeout as *const timespec as u64;
+        sqe.0.len = 1; // one timespec
+        sqe.0.off = count as u64;
+        sqe
+    }
+
+    /// Readiness the completion way: completes once `fd` is readable, one shot
+    pub fn poll_readable<'a>(fd: impl Into<SocketRef<'a>>) -> Self {
+        let mut sqe = Self::new(IORING_OP_POLL_ADD, fd.into().0);
+        sqe.0.op_flags = POLLIN as u32;
+        sqe
+    }
+
+    /// Cancels the operation pushed with `token`. Completes with 0 when it was,
+    /// ENOENT when it had completed already and EALREADY when it is running and
+    /// only gets interrupted. The cancelled operation completes too, with ECANCELED.
+    pub fn async_cancel(token: u64) -> Self {
+        let mut sqe = Self::new(IORING_OP_ASYNC_CANCEL, -1);
+        sqe.0.addr = token;
+        sqe
+    }
+
+    /// Comes back untouched in the `Completion`, like an epoll token
+    pub fn token(mut self, token: u64) -> Self {
+        self.0.user_data = token;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Completion {
+    pub token: u64,
+    /// What the syscall would have returned, `-errno` on failure
+    pub result: i32,
+}
+
+impl Completion {
+    /// The result as a syscall wrapper would return it, `op` names it in the error
+    pub fn result(&self, op: &'static str) -> Result<u32, SysError> {
+        if self.result < 0 {
+            return Err(SysError::new(op, -self.result));
+        }
+        Ok(self.result as u32)
+    }
+}
+
+impl From<io_uring_cqe> for Completion {
+    fn from(cqe: io_uring_cqe) -> Self {
+        Completion {
+            token: cqe.user_data,
+            result: cqe.res,
+        }
+    }
+}
+
+impl Display for Completion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Completion({}, {})", self.token, self.result)
+    }
+}
@@ -0,0 +1,6 @@
+//! Fd of an io_uring instance. The rings themselves are mapped from it,
+//! see `IoUring`. Closes the fd when dropped.
+
+pub struct IoUringFd(pub(crate) i32);
+
+owned_fd_type!(IoUringFd, "an open io_uring fd", "io_uring closed");
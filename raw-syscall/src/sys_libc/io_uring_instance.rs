@@ -0,0 +1,167 @@
+//! io_uring without liburing: two rings shared with the kernel through `mmap`.
+//!
+//! We write `Sqe`s into the submission ring and move its tail, the kernel
+//! moves the head as it takes them. It writes completions into the other ring
+//! and moves its tail, we move the head once we read them. The heads and
+//! tails are the only synchronization, so they are read and written as
+//! atomics. One `io_uring_enter` both hands over everything pushed since the
+//! last one and waits for completions.
+use super::libc::{
+    IORING_ENTER_GETEVENTS, IORING_FEAT_SINGLE_MMAP, IORING_OFF_CQ_RING, IORING_OFF_SQ_RING,
+    IORING_OFF_SQES, MAP_POPULATE, MAP_SHARED, PROT_READ, PROT_WRITE, io_uring_cqe,
+    io_uring_params, io_uring_sqe,
+};
+use super::{
+    Completion, Errno, IoUringFd, Mapping, Sqe, SysError, io_uring_enter, io_uring_setup, mmap,
+};
+use crate::cyan;
+use std::mem::size_of;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub struct IoUring {
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_entries: u32,
+    /// indexes into `sqes`, we always use slot n for ring position n
+    sq_array: *mut u32,
+    sqes: *mut io_uring_sqe,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const io_uring_cqe,
+    /// pushed but not handed to the kernel yet
+    unsubmitted: u32,
+    // dropped after the pointers above, and before the fd
+    _sq_ring: Mapping,
+    _cq_ring: Option<Mapping>,
+    _sqes_mapping: Mapping,
+    fd: IoUringFd,
+}
+
+impl IoUring {
+    /// Room for `entries` submissions in flight, the completion ring gets twice that
+    pub fn new(entries: u32) -> Result<Self, SysError> {
+        let mut params = io_uring_params::default();
+        let fd = io_uring_setup(entries, &mut params)?;
+        let (sq_off, cq_off) = (params.sq_off, params.cq_off);
+
+        let sq_ring_len = sq_off.array as usize + params.sq_entries as usize * size_of::<u32>();
+        let cq_ring_len =
+            cq_off.cqes as usize + params.cq_entries as usize * size_of::<io_uring_cqe>();
+        let prot = PROT_READ | PROT_WRITE;
+        let flags = MAP_SHARED | MAP_POPULATE;
+        // since 5.4 both rings live in one mapping, big enough for either
+        let single_mmap = params.features & IORING_FEAT_SINGLE_MMAP != 0;
+        let sq_ring_len = if single_mmap {
+            sq_ring_len.max(cq_ring_len)
+        } else {
+            sq_ring_len
+        };
+        let sq_ring = mmap(&fd, sq_ring_len, prot, flags, IORING_OFF_SQ_RING)?;
+        let cq_ring = match single_mmap {
+            true => None,
+            false => Some(mmap(&fd, cq_ring_len, prot, flags, IORING_OFF_CQ_RING)?),
+        };
+        let sqes_len = params.sq_entries as usize * size_of::<io_uring_sqe>();
+        let sqes_mapping = mmap(&fd, sqes_len, prot, flags, IORING_OFF_SQES)?;
+
+        let cq = cq_ring.as_ref().unwrap_or(&sq_ring);
+        let ring = IoUring {
+            sq_head: sq_ring.at(sq_off.head as usize),
+            sq_tail: sq_ring.at(sq_off.tail as usize),
+            sq_mask: unsafe { *sq_ring.at::<u32>(sq_off.ring_mask as usize) },
+            sq_entries: params.sq_entries,
+            sq_array: sq_ring.at(sq_off.array as usize),
+            sqes: sqes_mapping.at(0),
+            cq_head: cq.at(cq_off.head as usize),
+            cq_tail: cq.at(cq_off.tail as usize),
+            cq_mask: unsafe { *cq.at::<u32>(cq_off.ring_mask as usize) },
+            cqes: cq.at(cq_off.cqes as usize),
+            unsubmitted: 0,
+            _sq_ring: sq_ring,
+            _cq_ring: cq_ring,
+            _sqes_mapping: sqes_mapping,
+            fd,
+        };
+        cyan!(
+            "Created io_uring {} with {} submission and {} completion entries",
+            ring.fd,
+            params.sq_entries,
+            params.cq_entries
+        );
+        Ok(ring)
+    }
+
+    /// Queues `sqe` for the next `submit`. Submits right away if the ring is full,
+    /// EBUSY if the kernel still hasn't taken an entry after that.
+    ///
+    /// # Safety
+    /// The fd, buffer, address or timespec the `Sqe` was built from must
+    /// stay valid and in place until its completion was popped.
+    pub unsafe fn push(&mut self, sqe: Sqe) -> Result<(), SysError> {
+        // only we move the tail, the kernel moves the head
+        let tail = unsafe { &*self.sq_tail }.load(Ordering::Relaxed);
+        let head = unsafe { &*self.sq_head }.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == self.sq_entries {
+            self.submit()?;
+            // the kernel may have taken fewer than all, their slots are not free yet
+            let head = unsafe { &*self.sq_head }.load(Ordering::Acquire);
+            if tail.wrapping_sub(head) == self.sq_entries {
+                return Err(SysError::new("io_uring_enter", Errno::EBUSY.raw()));
+            }
+        }
+        let idx = tail & self.sq_mask;
+        unsafe {
+            *self.sqes.add(idx as usize) = sqe.0;
+            *self.sq_array.add(idx as usize) = idx;
+            // the entry is written before the kernel can see the new tail
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.unsubmitted += 1;
+        Ok(())
+    }
+
+    /// Hands everything pushed so far to the kernel without waiting
+    pub fn submit(&mut self) -> Result<u32, SysError> {
+        self.enter(0, 0)
+    }
+
+    /// Submits and blocks until at least `want` completions can be popped
+    pub fn submit_and_wait(&mut self, want: u32) -> Result<u32, SysError> {
+        self.enter(want, IORING_ENTER_GETEVENTS)
+    }
+
+    fn enter(&mut self, want: u32, flags: u32) -> Result<u32, SysError> {
+        let submitted = io_uring_enter(&self.fd, self.unsubmitted, want, flags, None)?;
+        self.unsubmitted -= submitted.min(self.unsubmitted);
+        Ok(submitted)
+    }
+
+    /// Next completion, None once the ring is empty. Never enters the kernel.
+    pub fn pop(&mut self) -> Option<Completion> {
+        // only we move the head, the kernel moves the tail
+        let head = unsafe { &*self.cq_head }.load(Ordering::Relaxed);
+        let tail = unsafe { &*self.cq_tail }.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let cqe = unsafe { *self.cqes.add((head & self.cq_mask) as usize) };
+        // the entry is copied out before the kernel may overwrite it
+        unsafe { &*self.cq_head }.store(head.wrapping_add(1), Ordering::Release);
+        Some(Completion::from(cqe))
+    }
+}
+
+impl AsFd for IoUring {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for IoUring {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
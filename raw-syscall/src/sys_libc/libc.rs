@@ -1,11 +1,32 @@
 //! External C function and struct definitions for socket programming.
-pub const EINPROGRESS: i32 = 115;
-pub const EWOULDBLOCK: i32 = 11;
-pub const EAGAIN: i32 = 11;
+//! errno values live in `errno.rs`.
+use std::time::Duration;
 
-pub const SO_ERROR: i32 = 4;
 pub const SOL_SOCKET: i32 = 1;
+pub const SO_REUSEADDR: i32 = 2;
+pub const SO_ERROR: i32 = 4;
+pub const SO_SNDBUF: i32 = 7;
+pub const SO_RCVBUF: i32 = 8;
+pub const SO_KEEPALIVE: i32 = 9;
+pub const SO_LINGER: i32 = 13;
+pub const SO_REUSEPORT: i32 = 15;
+pub const SO_RCVTIMEO: i32 = 20;
+pub const SO_TIMESTAMP: i32 = 29;
+
+// level IPPROTO_TCP options
+pub const TCP_NODELAY: i32 = 1;
+pub const TCP_KEEPIDLE: i32 = 4;
+
+// send/recv flags
+pub const MSG_CTRUNC: i32 = 0x08; // recvmsg: the control messages didn't fit
+pub const MSG_TRUNC: i32 = 0x20; // recv: return the real datagram length even if it didn't fit
+pub const MSG_CMSG_CLOEXEC: i32 = 0x40000000; // recvmsg: fds received with SCM_RIGHTS are close-on-exec
 
+// control message types at level SOL_SOCKET
+pub const SCM_RIGHTS: i32 = 1; // file descriptors, unix sockets only
+pub const SCM_TIMESTAMP: i32 = SO_TIMESTAMP; // a timeval, when the kernel received the data
+
+pub const AF_UNIX: i32 = 1; // Unix domain sockets (local)
 pub const AF_INET: i32 = 2; // IPv4 family
 pub const AF_INET6: i32 = 10; // IPv6 family
 
@@ -25,6 +46,7 @@ pub const POLLRDHUP: i16 = 0x2000;
 
 // Socket address structure
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct sockaddr_in {
     pub sin_family: u16,
     pub sin_port: u16,
@@ -32,6 +54,42 @@ pub struct sockaddr_in {
     pub sin_zero: [u8; 8],
 }
 
+// IPv6 socket address structure
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sockaddr_in6 {
+    pub sin6_family: u16,
+    pub sin6_port: u16,
+    pub sin6_flowinfo: u32,
+    pub sin6_addr: [u8; 16],
+    pub sin6_scope_id: u32,
+}
+
+// Unix domain socket address, `sun_path` is a NUL terminated path,
+// or starts with a NUL byte for the abstract namespace
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sockaddr_un {
+    pub sun_family: u16,
+    pub sun_path: [u8; 108],
+}
+
+// Big enough for any address family, used when the kernel picks the family
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sockaddr_storage {
+    pub ss_family: u16,
+    pub __ss_padding: [u8; 118],
+    pub __ss_align: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct linger {
+    pub l_onoff: i32,
+    pub l_linger: i32,
+}
+
 /// File descriptor set for select
 pub type fd_set = [u64; 16];
 
@@ -41,6 +99,35 @@ pub struct sockaddr {
     pub sa_data: [u8; 14],
 }
 
+/// One buffer of a scatter/gather call, same layout as std's `IoSlice`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct iovec {
+    pub iov_base: *mut core::ffi::c_void,
+    pub iov_len: usize,
+}
+
+/// Everything `sendmsg`/`recvmsg` take: address, buffers and control messages
+#[repr(C)]
+pub struct msghdr {
+    pub msg_name: *mut core::ffi::c_void,
+    pub msg_namelen: u32,
+    pub msg_iov: *mut iovec,
+    pub msg_iovlen: usize,
+    pub msg_control: *mut core::ffi::c_void,
+    pub msg_controllen: usize,
+    pub msg_flags: i32,
+}
+
+/// Header of one control message, its data follows aligned to 8 bytes
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct cmsghdr {
+    pub cmsg_len: usize, // header and data, without the trailing padding
+    pub cmsg_level: i32,
+    pub cmsg_type: i32,
+}
+
 #[repr(C)]
 pub struct pollfd {
     pub fd: i32,
@@ -50,6 +137,139 @@ pub struct pollfd {
 
 pub type nfds_t = u64;
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct timeval {
+    pub tv_sec: i64,
+    pub tv_usec: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+impl From<Duration> for timeval {
+    fn from(duration: Duration) -> Self {
+        timeval {
+            tv_sec: duration.as_secs().min(i64::MAX as u64) as i64,
+            tv_usec: duration.subsec_micros() as i64,
+        }
+    }
+}
+
+impl From<timeval> for Duration {
+    /// negative fields (never written by the kernel) count as zero
+    fn from(tv: timeval) -> Self {
+        Duration::from_secs(tv.tv_sec.max(0) as u64)
+            + Duration::from_micros(tv.tv_usec.max(0) as u64)
+    }
+}
+
+impl From<Duration> for timespec {
+    fn from(duration: Duration) -> Self {
+        timespec {
+            tv_sec: duration.as_secs().min(i64::MAX as u64) as i64,
+            tv_nsec: duration.subsec_nanos() as i64,
+        }
+    }
+}
+
+impl From<timespec> for Duration {
+    fn from(ts: timespec) -> Self {
+        Duration::from_secs(ts.tv_sec.max(0) as u64)
+            + Duration::from_nanos(ts.tv_nsec.max(0) as u64)
+    }
+}
+
+// --------------------------------------------------
+// clocks and timerfd
+// --------------------------------------------------
+
+pub const CLOCK_REALTIME: i32 = 0;
+pub const CLOCK_MONOTONIC: i32 = 1;
+pub const CLOCK_BOOTTIME: i32 = 7;
+
+pub const TFD_TIMER_ABSTIME: i32 = 1;
+pub const TFD_NONBLOCK: i32 = 0o4000;
+pub const TFD_CLOEXEC: i32 = 0o2000000;
+
+/// First expiry in `it_value` (zero disarms), then every `it_interval` (zero: only once)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct itimerspec {
+    pub it_interval: timespec,
+    pub it_value: timespec,
+}
+
+// --------------------------------------------------
+// signals (same numbers on x86_64 and aarch64)
+// --------------------------------------------------
+
+pub const SIGHUP: i32 = 1;
+pub const SIGINT: i32 = 2;
+pub const SIGQUIT: i32 = 3;
+pub const SIGUSR1: i32 = 10;
+pub const SIGUSR2: i32 = 12;
+pub const SIGPIPE: i32 = 13;
+pub const SIGALRM: i32 = 14;
+pub const SIGTERM: i32 = 15;
+pub const SIGCHLD: i32 = 17;
+
+/// glibc's signal set, room for 1024 signals. The kernel only reads the first 64 bits.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sigset_t {
+    pub __val: [u64; 16],
+}
+
+// how values for sigprocmask
+pub const SIG_BLOCK: i32 = 0;
+pub const SIG_UNBLOCK: i32 = 1;
+pub const SIG_SETMASK: i32 = 2;
+
+// signalfd flags
+pub const SFD_NONBLOCK: i32 = 0o4000;
+pub const SFD_CLOEXEC: i32 = 0o2000000;
+
+/// What a read from a signalfd returns, one per pending signal. Always 128 bytes.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct signalfd_siginfo {
+    pub ssi_signo: u32,
+    pub ssi_errno: i32,
+    pub ssi_code: i32,
+    pub ssi_pid: u32,
+    pub ssi_uid: u32,
+    pub ssi_fd: i32,
+    pub ssi_tid: u32,
+    pub ssi_band: u32,
+    pub ssi_overrun: u32,
+    pub ssi_trapno: u32,
+    pub ssi_status: i32,
+    pub ssi_int: i32,
+    pub ssi_ptr: u64,
+    pub ssi_utime: u64,
+    pub ssi_stime: u64,
+    pub ssi_addr: u64,
+    pub ssi_addr_lsb: u16,
+    pub __pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    pub __pad: [u8; 28],
+}
+
+// --------------------------------------------------
+// eventfd
+// --------------------------------------------------
+
+pub const EFD_SEMAPHORE: i32 = 1;
+pub const EFD_NONBLOCK: i32 = 0o4000;
+pub const EFD_CLOEXEC: i32 = 0o2000000;
+
 // --------------------------------------------------
 // epoll
 // --------------------------------------------------
@@ -93,6 +313,193 @@ pub union epoll_data_t {
     pub u64: u64,
 }
 
+/// Kernel 5.11+, same number on every architecture. glibc only wraps it since 2.35.
+pub const SYS_EPOLL_PWAIT2: i64 = 441;
+
+/// Through `syscall`, older glibcs don't export `epoll_pwait2`
+///
+/// # Safety
+/// The pointers must be valid for everything the kernel reads and writes through them.
+pub unsafe fn epoll_pwait2(
+    epfd: i32,
+    events: *mut epoll_event,
+    maxevents: i32,
+    timeout: *const timespec,
+    sigmask: *const sigset_t,
+) -> i32 {
+    // the kernel wants the size of its own sigset_t: 64 signals
+    unsafe {
+        syscall(
+            SYS_EPOLL_PWAIT2,
+            epfd,
+            events,
+            maxevents,
+            timeout,
+            sigmask,
+            8usize,
+        ) as i32
+    }
+}
+
+// --------------------------------------------------
+// pipes and zero-copy
+// --------------------------------------------------
+
+// pipe2 flags
+pub const O_NONBLOCK: i32 = 0o4000;
+pub const O_CLOEXEC: i32 = 0o2000000;
+
+// splice and tee flags
+pub const SPLICE_F_MOVE: u32 = 1; // a hint, move pages instead of copying
+pub const SPLICE_F_NONBLOCK: u32 = 2; // don't block on the pipe (the other fd has its own O_NONBLOCK)
+pub const SPLICE_F_MORE: u32 = 4; // more data follows, like MSG_MORE
+
+// --------------------------------------------------
+// mmap
+// --------------------------------------------------
+
+pub const PROT_READ: i32 = 1;
+pub const PROT_WRITE: i32 = 2;
+pub const MAP_SHARED: i32 = 0x01;
+pub const MAP_POPULATE: i32 = 0x8000; // fault the pages in now, not on first touch
+pub const MAP_FAILED: *mut core::ffi::c_void = !0 as *mut core::ffi::c_void;
+
+// --------------------------------------------------
+// io_uring (same numbers and layouts on every architecture)
+// --------------------------------------------------
+
+pub const SYS_IO_URING_SETUP: i64 = 425;
+pub const SYS_IO_URING_ENTER: i64 = 426;
+
+// mmap offsets of the three shared regions
+pub const IORING_OFF_SQ_RING: i64 = 0;
+pub const IORING_OFF_CQ_RING: i64 = 0x8000000;
+pub const IORING_OFF_SQES: i64 = 0x10000000;
+
+// io_uring_params.features
+pub const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0; // both rings in one mapping, 5.4+
+
+// io_uring_enter flags
+pub const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+
+// opcodes
+pub const IORING_OP_POLL_ADD: u8 = 6;
+pub const IORING_OP_TIMEOUT: u8 = 11;
+pub const IORING_OP_ASYNC_CANCEL: u8 = 14;
+pub const IORING_OP_CONNECT: u8 = 16;
+pub const IORING_OP_SEND: u8 = 26;
+pub const IORING_OP_RECV: u8 = 27;
+
+/// Where the fields of the submission ring are, as offsets into its mapping
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct io_sqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Where the fields of the completion ring are, as offsets into its mapping
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct io_cqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Filled in by `io_uring_setup`, we only pass the flags
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct io_uring_params {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: io_sqring_offsets,
+    pub cq_off: io_cqring_offsets,
+}
+
+/// Submission queue entry, 64 bytes. The unions of the C struct are flattened
+/// to the member we use: `off` is also `addr2`, `op_flags` is `msg_flags`,
+/// `timeout_flags`, `poll32_events`, ... depending on the opcode.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct io_uring_sqe {
+    pub opcode: u8,
+    pub flags: u8,
+    pub ioprio: u16,
+    pub fd: i32,
+    pub off: u64,
+    pub addr: u64,
+    pub len: u32,
+    pub op_flags: u32,
+    pub user_data: u64,
+    pub buf_index: u16,
+    pub personality: u16,
+    pub splice_fd_in: i32,
+    pub addr3: u64,
+    pub __pad2: [u64; 1],
+}
+
+/// Completion queue entry: the sqe's `user_data` and what the syscall would have returned (`-errno` on failure)
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct io_uring_cqe {
+    pub user_data: u64,
+    pub res: i32,
+    pub flags: u32,
+}
+
+/// Through `syscall`, glibc has no wrapper (that's liburing's job)
+///
+/// # Safety
+/// The pointers must be valid for everything the kernel reads and writes through them.
+pub unsafe fn io_uring_setup(entries: u32, params: *mut io_uring_params) -> i32 {
+    unsafe { syscall(SYS_IO_URING_SETUP, entries, params) as i32 }
+}
+
+/// Through `syscall`, `sig` swaps the signal mask while waiting like `epoll_pwait`
+///
+/// # Safety
+/// The pointers must be valid for everything the kernel reads and writes through them.
+pub unsafe fn io_uring_enter(
+    fd: i32,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sig: *const sigset_t,
+) -> i32 {
+    // the kernel wants the size of its own sigset_t: 64 signals
+    unsafe {
+        syscall(
+            SYS_IO_URING_ENTER,
+            fd,
+            to_submit,
+            min_complete,
+            flags,
+            sig,
+            8usize,
+        ) as i32
+    }
+}
+
 // --------------------------------------------------
 // External C function declarations
 // --------------------------------------------------
@@ -101,9 +508,69 @@ unsafe extern "C" {
     // syscalls-ish functions
     pub fn socket(domain: i32, type_: i32, protocol: i32) -> i32;
     pub fn connect(sockfd: i32, addr: *const sockaddr, addrlen: u32) -> i32;
+    pub fn bind(sockfd: i32, addr: *const sockaddr, addrlen: u32) -> i32;
+    pub fn listen(sockfd: i32, backlog: i32) -> i32;
+    pub fn accept4(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32, flags: i32) -> i32;
+    pub fn getsockname(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32) -> i32;
+    pub fn getpeername(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32) -> i32;
     pub fn send(sockfd: i32, buf: *const u8, len: usize, flags: i32) -> isize;
     pub fn recv(sockfd: i32, buf: *mut u8, len: usize, flags: i32) -> isize;
+    pub fn sendto(
+        sockfd: i32,
+        buf: *const u8,
+        len: usize,
+        flags: i32,
+        dest_addr: *const sockaddr,
+        addrlen: u32,
+    ) -> isize;
+    pub fn recvfrom(
+        sockfd: i32,
+        buf: *mut u8,
+        len: usize,
+        flags: i32,
+        src_addr: *mut sockaddr,
+        addrlen: *mut u32,
+    ) -> isize;
+    pub fn socketpair(domain: i32, type_: i32, protocol: i32, sv: *mut [i32; 2]) -> i32;
+    pub fn sendmsg(sockfd: i32, msg: *const msghdr, flags: i32) -> isize;
+    pub fn recvmsg(sockfd: i32, msg: *mut msghdr, flags: i32) -> isize;
     pub fn close(fd: i32) -> i32;
+    pub fn read(fd: i32, buf: *mut u8, count: usize) -> isize;
+    pub fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+    pub fn readv(fd: i32, iov: *const iovec, iovcnt: i32) -> isize;
+    pub fn pipe2(fds: *mut [i32; 2], flags: i32) -> i32;
+    pub fn sendfile(out_fd: i32, in_fd: i32, offset: *mut i64, count: usize) -> isize;
+    pub fn splice(
+        fd_in: i32,
+        off_in: *mut i64,
+        fd_out: i32,
+        off_out: *mut i64,
+        len: usize,
+        flags: u32,
+    ) -> isize;
+    pub fn tee(fd_in: i32, fd_out: i32, len: usize, flags: u32) -> isize;
+    pub fn writev(fd: i32, iov: *const iovec, iovcnt: i32) -> isize;
+    pub fn mmap(
+        addr: *mut core::ffi::c_void,
+        length: usize,
+        prot: i32,
+        flags: i32,
+        fd: i32,
+        offset: i64,
+    ) -> *mut core::ffi::c_void;
+    pub fn munmap(addr: *mut core::ffi::c_void, length: usize) -> i32;
+    pub fn eventfd(initval: u32, flags: i32) -> i32;
+    pub fn clock_gettime(clockid: i32, tp: *mut timespec) -> i32;
+    pub fn sigprocmask(how: i32, set: *const sigset_t, oldset: *mut sigset_t) -> i32;
+    pub fn signalfd(fd: i32, mask: *const sigset_t, flags: i32) -> i32;
+    pub fn timerfd_create(clockid: i32, flags: i32) -> i32;
+    pub fn timerfd_settime(
+        fd: i32,
+        flags: i32,
+        new_value: *const itimerspec,
+        old_value: *mut itimerspec,
+    ) -> i32;
+    pub fn timerfd_gettime(fd: i32, curr_value: *mut itimerspec) -> i32;
     pub fn getsockopt(
         sockfd: i32,
         level: i32,
@@ -111,18 +578,44 @@ unsafe extern "C" {
         optval: *mut u8,
         optlen: *mut u32,
     ) -> i32;
+    pub fn setsockopt(sockfd: i32, level: i32, optname: i32, optval: *const u8, optlen: u32)
+    -> i32;
     pub fn select(
         nfds: i32,
         readfds: *mut fd_set,
         writefds: *mut fd_set,
         exceptfds: *mut fd_set,
-        timeout: *mut u8,
+        timeout: *mut timeval,
+    ) -> i32;
+    pub fn pselect(
+        nfds: i32,
+        readfds: *mut fd_set,
+        writefds: *mut fd_set,
+        exceptfds: *mut fd_set,
+        timeout: *const timespec,
+        sigmask: *const sigset_t,
     ) -> i32;
     pub fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: i32) -> i32;
+    pub fn ppoll(
+        fds: *mut pollfd,
+        nfds: nfds_t,
+        timeout: *const timespec,
+        sigmask: *const sigset_t,
+    ) -> i32;
     pub fn epoll_create(size: i32) -> i32;
     pub fn epoll_create1(flags: i32) -> i32;
     pub fn epoll_ctl(epfd: i32, op: i32, fd: i32, event: *mut epoll_event) -> i32;
     pub fn epoll_wait(epfd: i32, events: *mut epoll_event, maxevents: i32, timeout: i32) -> i32;
+    pub fn epoll_pwait(
+        epfd: i32,
+        events: *mut epoll_event,
+        maxevents: i32,
+        timeout: i32,
+        sigmask: *const sigset_t,
+    ) -> i32;
+
+    // for calls glibc has no wrapper for (or only in recent versions)
+    pub fn syscall(number: i64, ...) -> i64;
 
     // helpers that are not syscalls (could be pure Rust)
     pub fn inet_addr(cp: *const i8) -> u32;
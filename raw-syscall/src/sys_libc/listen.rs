@@ -0,0 +1,14 @@
+use super::{SocketFd, SysError};
+use crate::cyan;
+
+/// Largest backlog the kernel accepts by default (`net.core.somaxconn`)
+pub const SOMAXCONN: i32 = 4096;
+
+pub fn listen(sockfd: &SocketFd, backlog: i32) -> Result<(), SysError> {
+    let result = unsafe { syscall!(listen(sockfd.0, backlog)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("listen"));
+    }
+    cyan!("Listening on {} (backlog {})", sockfd, backlog);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Memory shared with the kernel through `mmap`, unmapped when dropped.
+//! Only hands out raw pointers: what lives in there and who else writes
+//! to it (the kernel, for io_uring's rings) is up to the caller.
+use std::fmt::Display;
+
+pub struct Mapping {
+    pub(crate) ptr: *mut u8,
+    pub(crate) len: usize,
+}
+
+impl Mapping {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pointer `offset` bytes into the mapping, panics past the end
+    pub fn at<T>(&self, offset: usize) -> *mut T {
+        assert!(
+            offset + std::mem::size_of::<T>() <= self.len,
+            "offset {} out of {}",
+            offset,
+            self
+        );
+        // SAFETY: in bounds, checked above
+        unsafe { self.ptr.add(offset) as *mut T }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { syscall!(munmap(self.ptr as *mut _, self.len)) };
+    }
+}
+
+impl Display for Mapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mapping({:p}, {} bytes)", self.ptr, self.len)
+    }
+}
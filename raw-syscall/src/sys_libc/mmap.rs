@@ -0,0 +1,33 @@
+use super::libc::MAP_FAILED;
+use super::{Mapping, SocketRef, SysError};
+use std::ptr;
+
+/// Maps `len` bytes of `fd` at `offset`, wherever the kernel likes.
+/// `prot` is `PROT_READ`/`PROT_WRITE`, `flags` needs one of `MAP_SHARED` or `MAP_PRIVATE`.
+pub fn mmap<'a>(
+    fd: impl Into<SocketRef<'a>>,
+    len: usize,
+    prot: i32,
+    flags: i32,
+    offset: i64,
+) -> Result<Mapping, SysError> {
+    let fd = fd.into();
+    let addr = unsafe { syscall!(mmap(ptr::null_mut(), len, prot, flags, fd.0, offset)) };
+    if addr == MAP_FAILED {
+        return Err(SysError::last_os_error("mmap"));
+    }
+    Ok(Mapping {
+        ptr: addr as *mut u8,
+        len,
+    })
+}
+
+/// Unmaps it now instead of waiting for the drop, which can't report errors
+pub fn munmap(mapping: Mapping) -> Result<(), SysError> {
+    let mapping = std::mem::ManuallyDrop::new(mapping);
+    let result = unsafe { syscall!(munmap(mapping.ptr as *mut _, mapping.len)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("munmap"));
+    }
+    Ok(())
+}
@@ -1,31 +1,154 @@
 //! A module of rust abstractions over libc
+//!
+//! With the `raw-syscalls` feature the wrappers skip libc and issue the
+//! syscalls themselves through `sys_raw`. Types and constants always come from `libc.rs`.
+
+/// Calls `sys::$fn`, counting it in `stats`. Every wrapper goes through here.
+macro_rules! syscall {
+    ($fn:ident($($arg:expr),* $(,)?)) => {{
+        $crate::sys_libc::stats::count_syscall();
+        $crate::sys_libc::sys::$fn($($arg),*)
+    }};
+}
+
+// before the fd wrappers, a macro is only visible after its definition
+#[macro_use]
+mod owned_fd;
+
+pub mod accept;
+pub mod bind;
+pub mod clock_gettime;
+pub mod cmsg;
 pub mod connect;
+pub mod dns_message;
 pub mod epoll;
 pub mod epoll_event;
 pub mod epoll_fd;
+pub mod epoll_instance;
+pub mod errno;
+pub mod event_fd;
+pub mod eventfd;
+pub mod events;
 pub mod fd_set;
 pub mod getsockopt;
+pub mod hosts;
+pub mod interest;
+pub mod io_uring;
+pub mod io_uring_entry;
+pub mod io_uring_fd;
+pub mod io_uring_instance;
 pub mod libc;
+pub mod listen;
+pub mod mapping;
+pub mod mmap;
 pub mod net_utils;
+pub mod pipe2;
+pub mod pipe_fd;
 pub mod poll;
 pub mod poll_fd;
+pub mod read;
+pub mod readv;
 pub mod recv;
+pub mod recvfrom;
+pub mod recvmsg;
+pub mod resolv_conf;
+pub mod resolver;
+pub mod retry;
 pub mod select;
 pub mod send;
+pub mod sendfile;
+pub mod sendmsg;
+pub mod sendto;
+pub mod setsockopt;
+pub mod sig_set;
+pub mod signal_fd;
+pub mod signalfd;
+pub mod sigprocmask;
+pub mod sock_addr;
 pub mod socket;
 pub mod socket_fd;
+pub mod socket_ref;
+pub mod socketpair;
+pub mod sockname;
+pub mod sockopt;
+pub mod splice;
+pub mod stats;
+pub mod timer_fd;
+pub mod timerfd;
+pub mod write;
+pub mod writev;
+
+#[cfg(feature = "raw-syscalls")]
+pub(crate) use crate::sys_raw as sys;
+#[cfg(not(feature = "raw-syscalls"))]
+pub(crate) use libc as sys;
 
+pub use accept::accept4;
+pub use bind::bind;
+pub use clock_gettime::clock_gettime;
+pub use cmsg::{ControlBuffer, ControlMessage, ReceivedControl, cmsg_space};
 pub use connect::connect;
-pub use epoll::{epoll_create1, epoll_ctl, epoll_ctl_remove, epoll_wait};
+pub use dns_message::DnsResponse;
+pub use epoll::{
+    epoll_create1, epoll_ctl, epoll_ctl_remove, epoll_pwait, epoll_pwait2, epoll_wait,
+};
 pub use epoll_event::EpollEvent;
 pub use epoll_fd::EpollFd;
-pub use fd_set::FdSet;
+pub use epoll_instance::Epoll;
+pub use errno::{Errno, SysError};
+pub use event_fd::EventFd;
+pub use eventfd::eventfd;
+pub use events::Events;
+pub use fd_set::{DynFdSet, FD_SETSIZE, FdSet, FdSetIter, SelectSet};
 pub use getsockopt::{get_socket_error, getsockopt};
+pub use hosts::{HOSTS_PATH, lookup_hosts};
+pub use interest::{Interest, Readiness};
+pub use io_uring::{io_uring_enter, io_uring_setup};
+pub use io_uring_entry::{Completion, Sqe};
+pub use io_uring_fd::IoUringFd;
+pub use io_uring_instance::IoUring;
+pub use listen::{SOMAXCONN, listen};
+pub use mapping::Mapping;
+pub use mmap::{mmap, munmap};
 pub use net_utils::create_ipv4_sockaddr;
-pub use poll::poll;
+pub use pipe_fd::PipeFd;
+pub use pipe2::pipe2;
+pub use poll::{poll, ppoll};
 pub use poll_fd::PollFd;
+pub use read::read;
+pub use readv::readv;
 pub use recv::recv;
-pub use select::{select, select_read, select_write};
+pub use recvfrom::recvfrom;
+pub use recvmsg::{ReceivedMsg, recvmsg};
+pub use resolv_conf::{RESOLV_CONF_PATH, ResolvConf};
+pub use resolver::{DnsQuery, resolve, resolve_on};
+pub use retry::{retry, retry_with_timeout, set_restart_on_eintr};
+pub use select::{pselect, select, select_read, select_write};
 pub use send::send;
-pub use socket::{create_non_blocking_tcp_socket, create_tcp_socket};
+pub use sendfile::sendfile;
+pub use sendmsg::sendmsg;
+pub use sendto::sendto;
+pub use setsockopt::setsockopt;
+pub use sig_set::{SigSet, signal_name};
+pub use signal_fd::SignalFd;
+pub use signalfd::signalfd;
+pub use sigprocmask::sigprocmask;
+pub use sock_addr::SockAddr;
+pub use socket::{
+    SOCK_CLOEXEC, SOCK_DGRAM, SOCK_NONBLOCK, SOCK_STREAM, create_non_blocking_tcp_socket,
+    create_non_blocking_udp_socket, create_tcp_socket, create_udp_socket,
+    create_unix_datagram_socket, create_unix_stream_socket,
+};
 pub use socket_fd::SocketFd;
+pub use socket_ref::SocketRef;
+pub use socketpair::socketpair;
+pub use sockname::{getpeername, getsockname};
+pub use sockopt::{
+    KeepAlive, Linger, RcvBuf, RcvTimeo, ReuseAddr, ReusePort, SndBuf, SockOpt, TcpKeepIdle,
+    TcpNoDelay, Timestamp, get_opt, set_opt,
+};
+pub use splice::{splice, tee};
+pub use timer_fd::{TimerFd, TimerSpec};
+pub use timerfd::{timerfd_create, timerfd_gettime, timerfd_settime};
+pub use write::write;
+pub use writev::writev;
@@ -0,0 +1,63 @@
+//! What every fd wrapper shares: borrowing the fd, handing it over, converting
+//! from and to `OwnedFd`, closing it when dropped and printing it.
+
+/// The impls for `struct $name(pub(crate) i32)` that owns its fd.
+/// `$what` is what the fd must be for `from_raw_fd`, `$closed` what drop logs.
+macro_rules! owned_fd_type {
+    ($name:ident, $what:literal, $closed:literal) => {
+        impl std::os::fd::AsFd for $name {
+            fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+                // SAFETY: the fd is open until we are dropped
+                unsafe { std::os::fd::BorrowedFd::borrow_raw(self.0) }
+            }
+        }
+
+        impl std::os::fd::AsRawFd for $name {
+            fn as_raw_fd(&self) -> std::os::fd::RawFd {
+                self.0
+            }
+        }
+
+        impl std::os::fd::FromRawFd for $name {
+            #[doc = concat!("SAFETY: `fd` must be ", $what, " nobody else will close")]
+            unsafe fn from_raw_fd(fd: std::os::fd::RawFd) -> Self {
+                $name(fd)
+            }
+        }
+
+        impl std::os::fd::IntoRawFd for $name {
+            /// Hands the fd over to the caller, it won't be closed on drop
+            fn into_raw_fd(self) -> std::os::fd::RawFd {
+                std::mem::ManuallyDrop::new(self).0
+            }
+        }
+
+        impl From<std::os::fd::OwnedFd> for $name {
+            fn from(fd: std::os::fd::OwnedFd) -> Self {
+                $name(std::os::fd::IntoRawFd::into_raw_fd(fd))
+            }
+        }
+
+        impl From<$name> for std::os::fd::OwnedFd {
+            fn from(fd: $name) -> Self {
+                // SAFETY: ownership moves from one wrapper to the other
+                unsafe {
+                    std::os::fd::FromRawFd::from_raw_fd(std::os::fd::IntoRawFd::into_raw_fd(fd))
+                }
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                unsafe { syscall!(close(self.0)) };
+                $crate::cyan!(concat!($closed, " {}"), self);
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, concat!(stringify!($name), "({})"), self.0)
+            }
+        }
+    };
+}
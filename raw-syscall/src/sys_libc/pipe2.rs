@@ -0,0 +1,14 @@
+use super::{PipeFd, SysError};
+use crate::cyan;
+
+/// A pipe as (read end, write end). `flags` is any of `O_NONBLOCK` and `O_CLOEXEC`.
+pub fn pipe2(flags: i32) -> Result<(PipeFd, PipeFd), SysError> {
+    let mut fds = [0; 2];
+    let result = unsafe { syscall!(pipe2(&mut fds, flags)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("pipe2"));
+    }
+    let pipe = (PipeFd(fds[0]), PipeFd(fds[1]));
+    cyan!("Created pipe: {} -> {}", pipe.1, pipe.0);
+    Ok(pipe)
+}
@@ -0,0 +1,6 @@
+//! One end of a pipe, from `pipe2`. Besides `read` and `write` it is what `splice`
+//! and `tee` need on at least one side. Closes the fd when dropped.
+
+pub struct PipeFd(pub(crate) i32);
+
+owned_fd_type!(PipeFd, "an open pipe end", "Pipe closed");
@@ -1,14 +1,50 @@
-use super::libc::pollfd;
-use super::{PollFd, SocketFd};
+use super::libc::{pollfd, timespec};
+use super::retry::restartable_with_timeout;
+use super::{PollFd, SigSet, SysError};
+use std::time::Duration;
 
-pub fn poll(fds: &mut [PollFd], timeout: i32) -> Result<i32, anyhow::Error> {
+pub fn poll(fds: &mut [PollFd], timeout: i32) -> Result<i32, SysError> {
     let nfds = fds.len() as super::libc::nfds_t;
-    let result = unsafe { super::libc::poll(fds.as_mut_ptr() as *mut pollfd, nfds, timeout) };
+    restartable_with_timeout(timeout, |timeout| {
+        let result = unsafe { syscall!(poll(fds.as_mut_ptr() as *mut pollfd, nfds, timeout)) };
+        super::stats::count_wakeup();
 
+        if result == -1 {
+            return Err(SysError::last_os_error("poll"));
+        }
+
+        Ok(result)
+    })
+}
+
+/// `poll` with a nanosecond timeout (None waits forever) and the signal mask
+/// swapped in for the duration of the wait, see `pselect`. Never restarted.
+pub fn ppoll(
+    fds: &mut [PollFd],
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> Result<i32, SysError> {
+    let nfds = fds.len() as super::libc::nfds_t;
+    let timeout = timeout.map(timespec::from);
+    let timeout_ptr = match timeout.as_ref() {
+        Some(ts) => ts as *const timespec,
+        None => std::ptr::null(),
+    };
+    let sigmask = match sigmask {
+        Some(sigmask) => sigmask.as_ptr(),
+        None => std::ptr::null(),
+    };
+    let result = unsafe {
+        syscall!(ppoll(
+            fds.as_mut_ptr() as *mut pollfd,
+            nfds,
+            timeout_ptr,
+            sigmask
+        ))
+    };
+    super::stats::count_wakeup();
     if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("Poll failed: {}", errno));
+        return Err(SysError::last_os_error("ppoll"));
     }
-
     Ok(result)
 }
@@ -1,15 +1,16 @@
-use super::SocketFd;
+use super::SocketRef;
 use super::libc::pollfd;
 use core::fmt::{Debug, Display};
 use core::marker::PhantomData;
 
-/// Ties the lifetime of the pollfd to the lifetime of the SocketFd
+/// Ties the lifetime of the pollfd to the lifetime of the socket
 /// so that we don't have dangling fds in the pollfd array,
 /// while maintaining the same memory layout as pollfd
-pub struct PollFd<'a>(pollfd, PhantomData<&'a SocketFd>);
+pub struct PollFd<'a>(pollfd, PhantomData<SocketRef<'a>>);
 
 impl<'a> PollFd<'a> {
-    pub fn new(sockfd: &'a SocketFd, events: i16) -> Self {
+    pub fn new(sockfd: impl Into<SocketRef<'a>>, events: i16) -> Self {
+        let sockfd = sockfd.into();
         PollFd(
             pollfd {
                 fd: sockfd.0,
@@ -20,8 +21,9 @@ impl<'a> PollFd<'a> {
         )
     }
 
-    // SAFETY: The caller must ensure that the output fd will not outlive the
-    // underlining SocketFd
+    /// # Safety
+    /// The caller must ensure that the output fd will not outlive the
+    /// underlining socket
     pub unsafe fn fd(&self) -> i32 {
         self.0.fd
     }
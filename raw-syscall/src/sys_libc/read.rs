@@ -0,0 +1,20 @@
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+
+/// `read(2)` on any fd (eventfd, timerfd, pipe, ...), sockets have `recv`.
+/// Ok(None) when it would block, Ok(Some(0)) at end of file.
+pub fn read<'a>(fd: impl Into<SocketRef<'a>>, buf: &mut [u8]) -> Result<Option<usize>, SysError> {
+    let fd = fd.into();
+    let bytes_read = restartable(|| {
+        let bytes_read = unsafe { syscall!(read(fd.0, buf.as_mut_ptr(), buf.len())) };
+        if bytes_read == -1 {
+            return Err(SysError::last_os_error("read"));
+        }
+        Ok(bytes_read as usize)
+    });
+    match bytes_read {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_read) => Ok(Some(bytes_read)),
+    }
+}
@@ -0,0 +1,27 @@
+use super::libc::iovec;
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+use std::io::IoSliceMut;
+
+/// `read` into several buffers in one call, the first is filled before the next.
+/// Ok(None) when it would block, Ok(Some(0)) at end of file.
+pub fn readv<'a>(
+    fd: impl Into<SocketRef<'a>>,
+    bufs: &mut [IoSliceMut],
+) -> Result<Option<usize>, SysError> {
+    let fd = fd.into();
+    // IoSliceMut is guaranteed to be an iovec on unix
+    let iov = bufs.as_mut_ptr() as *const iovec;
+    let bytes_read = restartable(|| {
+        let bytes_read = unsafe { syscall!(readv(fd.0, iov, bufs.len() as i32)) };
+        if bytes_read == -1 {
+            return Err(SysError::last_os_error("readv"));
+        }
+        Ok(bytes_read as usize)
+    });
+    match bytes_read {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_read) => Ok(Some(bytes_read)),
+    }
+}
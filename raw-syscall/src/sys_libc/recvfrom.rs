@@ -0,0 +1,59 @@
+use super::libc::{MSG_TRUNC, sockaddr, sockaddr_storage};
+use super::retry::restartable;
+use super::{Errno, SockAddr, SocketFd, SysError};
+use crate::cyan;
+use std::mem;
+
+/// Receives one datagram and the address it came from.
+/// Returns the full length of the datagram, which is larger than `buf`
+/// when it didn't fit: the bytes past `buf.len()` are lost, one call never
+/// returns half a datagram and the next call the other half.
+/// If no datagram is waiting on a non-blocking socket, returns Ok(None).
+pub fn recvfrom(sockfd: &SocketFd, buf: &mut [u8]) -> Result<Option<(usize, SockAddr)>, SysError> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = 0;
+    let bytes_received = restartable(|| {
+        len = mem::size_of::<sockaddr_storage>() as u32;
+        let addr = &mut storage as *mut sockaddr_storage as *mut sockaddr;
+        let bytes_received = unsafe {
+            syscall!(recvfrom(
+                sockfd.0,
+                buf.as_mut_ptr(),
+                buf.len(),
+                MSG_TRUNC,
+                addr,
+                &mut len,
+            ))
+        };
+        if bytes_received == -1 {
+            return Err(SysError::last_os_error("recvfrom"));
+        }
+        Ok(bytes_received as usize)
+    });
+    match bytes_received {
+        Err(err) if err.is_would_block() => {
+            cyan!(
+                "Would block, no datagram available on {}, ({})",
+                sockfd,
+                err
+            );
+            Ok(None)
+        }
+        Err(err) => Err(err),
+        Ok(bytes_received) => {
+            let from = SockAddr::from_storage(&storage, len)
+                .ok_or(SysError::new("recvfrom", Errno::EAFNOSUPPORT.raw()))?;
+            if bytes_received > buf.len() {
+                cyan!(
+                    "Received {} byte datagram from {}, truncated to {}",
+                    bytes_received,
+                    from,
+                    buf.len()
+                );
+            } else {
+                cyan!("Received {} bytes from {}", bytes_received, from);
+            }
+            Ok(Some((bytes_received, from)))
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::cmsg::{ControlBuffer, ReceivedControl};
+use super::libc::{MSG_CMSG_CLOEXEC, MSG_CTRUNC, MSG_TRUNC, iovec, msghdr, sockaddr_storage};
+use super::retry::restartable;
+use super::{SockAddr, SocketFd, SysError};
+use crate::cyan;
+use std::io::IoSliceMut;
+use std::mem;
+
+/// What came with the bytes
+pub struct ReceivedMsg {
+    pub bytes: usize,
+    /// the sender, None on connected sockets
+    pub from: Option<SockAddr>,
+    pub control: Vec<ReceivedControl>,
+    /// a datagram was longer than the buffers, the rest is gone
+    pub truncated: bool,
+    /// the control messages didn't fit into the `ControlBuffer`
+    pub control_truncated: bool,
+}
+
+/// `recv` into several buffers at once, plus the control messages that came
+/// with the data. Fds received with `SCM_RIGHTS` are close-on-exec.
+/// If no data is available or would block, returns Ok(None).
+pub fn recvmsg(
+    sockfd: &SocketFd,
+    bufs: &mut [IoSliceMut],
+    control: &mut ControlBuffer,
+) -> Result<Option<ReceivedMsg>, SysError> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut msg = msghdr {
+        msg_name: &mut storage as *mut sockaddr_storage as *mut _,
+        msg_namelen: 0,
+        // IoSliceMut is guaranteed to be an iovec on unix
+        msg_iov: bufs.as_mut_ptr() as *mut iovec,
+        msg_iovlen: bufs.len(),
+        msg_control: control.as_mut_ptr() as *mut _,
+        msg_controllen: 0,
+        msg_flags: 0,
+    };
+    let bytes_received = restartable(|| {
+        // both are in-out, the kernel writes back what it used
+        msg.msg_namelen = mem::size_of::<sockaddr_storage>() as u32;
+        msg.msg_controllen = control.capacity();
+        let bytes_received = unsafe { syscall!(recvmsg(sockfd.0, &mut msg, MSG_CMSG_CLOEXEC)) };
+        if bytes_received == -1 {
+            return Err(SysError::last_os_error("recvmsg"));
+        }
+        Ok(bytes_received as usize)
+    });
+    match bytes_received {
+        Err(err) if err.is_would_block() => {
+            cyan!("Would block, no data available on {}, ({})", sockfd, err);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+        Ok(bytes_received) => {
+            control.set_len(msg.msg_controllen);
+            let received = ReceivedMsg {
+                bytes: bytes_received,
+                from: match msg.msg_namelen {
+                    0 => None,
+                    len => SockAddr::from_storage(&storage, len),
+                },
+                control: control.decode(),
+                truncated: msg.msg_flags & MSG_TRUNC != 0,
+                control_truncated: msg.msg_flags & MSG_CTRUNC != 0,
+            };
+            cyan!(
+                "Received {} bytes with {} control messages",
+                received.bytes,
+                received.control.len()
+            );
+            Ok(Some(received))
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! `/etc/resolv.conf`: which nameservers to ask, how long to wait for them and
+//! which search domains to try for short names.
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+pub const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+#[derive(Clone, Debug)]
+pub struct ResolvConf {
+    pub nameservers: Vec<SocketAddr>,
+    pub search: Vec<String>,
+    /// Names with at least this many dots are tried as they are before the search domains
+    pub ndots: usize,
+    /// Per query and nameserver
+    pub timeout: Duration,
+    /// Rounds over all the nameservers
+    pub attempts: u32,
+}
+
+impl Default for ResolvConf {
+    /// glibc's defaults, without nameservers: `parse` adds one on this host if none is listed
+    fn default() -> Self {
+        ResolvConf {
+            nameservers: vec![],
+            search: vec![],
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+}
+
+impl ResolvConf {
+    /// A missing file gets the defaults
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::parse("")),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Unknown lines and options are skipped, like the libc parser does
+    pub fn parse(text: &str) -> Self {
+        let mut conf = ResolvConf::default();
+        for line in text.lines() {
+            let line = line.split(['#', ';']).next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => {
+                    // IPv6 scope ids (fe80::1%eth0) don't parse, that nameserver is skipped
+                    if let Some(ip) = fields.next().and_then(|ip| ip.parse::<IpAddr>().ok()) {
+                        conf.nameservers.push(SocketAddr::new(ip, 53));
+                    }
+                }
+                // the last of search and domain wins
+                Some("search") => conf.search = fields.map(str::to_string).collect(),
+                Some("domain") => conf.search = fields.take(1).map(str::to_string).collect(),
+                Some("options") => {
+                    for option in fields {
+                        let (name, value) = option.split_once(':').unwrap_or((option, ""));
+                        let value = value.parse::<u32>().ok();
+                        match (name, value) {
+                            ("ndots", Some(n)) => conf.ndots = n.min(15) as usize,
+                            ("timeout", Some(n)) => conf.timeout = Duration::from_secs(n.into()),
+                            ("attempts", Some(n)) => conf.attempts = n.clamp(1, 5),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if conf.nameservers.is_empty() {
+            conf.nameservers
+                .push(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 53));
+        }
+        conf
+    }
+
+    /// The names to query for `name`, in order. A trailing dot means the name
+    /// is complete and the search domains are left out.
+    pub fn candidates(&self, name: &str) -> Vec<String> {
+        if let Some(name) = name.strip_suffix('.') {
+            return vec![name.to_string()];
+        }
+        let searched = self
+            .search
+            .iter()
+            .map(|domain| format!("{}.{}", name, domain));
+        if name.matches('.').count() >= self.ndots {
+            std::iter::once(name.to_string()).chain(searched).collect()
+        } else {
+            searched.chain(std::iter::once(name.to_string())).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nameservers_search_and_options() {
+        let conf = ResolvConf::parse(
+            "# generated\n\
+             nameserver 192.0.2.53\n\
+             nameserver 2001:db8::53 ; the v6 one\n\
+             nameserver fe80::1%eth0\n\
+             search corp.example example.com\n\
+             options ndots:2 timeout:1 attempts:3 rotate\n",
+        );
+        assert_eq!(
+            conf.nameservers,
+            vec![
+                "192.0.2.53:53".parse().unwrap(),
+                "[2001:db8::53]:53".parse().unwrap()
+            ]
+        );
+        assert_eq!(conf.search, vec!["corp.example", "example.com"]);
+        assert_eq!(conf.ndots, 2);
+        assert_eq!(conf.timeout, Duration::from_secs(1));
+        assert_eq!(conf.attempts, 3);
+    }
+
+    #[test]
+    fn empty_file_gets_defaults_and_localhost() {
+        let conf = ResolvConf::parse("");
+        assert_eq!(conf.nameservers, vec!["127.0.0.1:53".parse().unwrap()]);
+        assert!(conf.search.is_empty());
+        assert_eq!(conf.ndots, 1);
+        assert_eq!(conf.attempts, 2);
+    }
+
+    #[test]
+    fn last_of_search_and_domain_wins() {
+        let conf = ResolvConf::parse("search a.example b.example\ndomain c.example\n");
+        assert_eq!(conf.search, vec!["c.example"]);
+        let conf = ResolvConf::parse("domain c.example\nsearch a.example b.example\n");
+        assert_eq!(conf.search, vec!["a.example", "b.example"]);
+    }
+
+    #[test]
+    fn clamps_options() {
+        let conf = ResolvConf::parse("options ndots:30 attempts:0\n");
+        assert_eq!(conf.ndots, 15);
+        assert_eq!(conf.attempts, 1);
+    }
+
+    #[test]
+    fn short_names_try_search_domains_first() {
+        let conf = ResolvConf::parse("search a.example b.example\noptions ndots:2\n");
+        assert_eq!(
+            conf.candidates("host.lan"),
+            vec!["host.lan.a.example", "host.lan.b.example", "host.lan"]
+        );
+    }
+
+    #[test]
+    fn names_with_ndots_go_as_they_are_first() {
+        let conf = ResolvConf::parse("search a.example b.example\noptions ndots:2\n");
+        assert_eq!(
+            conf.candidates("www.example.com"),
+            vec![
+                "www.example.com",
+                "www.example.com.a.example",
+                "www.example.com.b.example"
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_dot_skips_search() {
+        let conf = ResolvConf::parse("search a.example\n");
+        assert_eq!(conf.candidates("host."), vec!["host"]);
+    }
+}
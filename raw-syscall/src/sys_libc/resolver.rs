@@ -0,0 +1,218 @@
+//! Hostname resolution without `getaddrinfo`: `/etc/hosts` first, then A and
+//! AAAA queries over UDP to the nameservers in `/etc/resolv.conf`.
+//!
+//! `DnsQuery` is a non-blocking UDP socket with both questions sent, it can sit
+//! in any epoll loop next to other sockets and is fed `on_readable` when its
+//! token comes up. `resolve_on` runs the queries in the caller's epoll, that is
+//! how `non_blocking_epoll` resolves before it connects. `resolve` is the same
+//! thing with an epoll of its own, for when there is nothing else to wait on.
+use super::dns_message::{self, RCODE_NXDOMAIN, TYPE_A, TYPE_AAAA};
+use super::hosts::{HOSTS_PATH, lookup_hosts};
+use super::{Epoll, Events, Interest, ResolvConf, SockAddr, SocketFd};
+use crate::cyan;
+use std::net::{IpAddr, SocketAddr};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Without EDNS a server never sends more over UDP, it sets the truncated flag instead
+const MAX_UDP_RESPONSE: usize = 512;
+
+struct Question {
+    id: u16,
+    qtype: u16,
+    /// None until the answer came in, NXDOMAIN is an empty answer
+    answer: Option<Vec<IpAddr>>,
+}
+
+pub struct DnsQuery {
+    socket: SocketFd,
+    nameserver: SocketAddr,
+    name: String,
+    questions: [Question; 2],
+}
+
+impl DnsQuery {
+    /// Sends the A and AAAA questions for `name` to `nameserver` right away
+    pub fn new(name: &str, nameserver: SocketAddr) -> Result<Self, anyhow::Error> {
+        let server_addr = SockAddr::from(nameserver);
+        let socket = super::create_non_blocking_udp_socket(server_addr.family())?;
+        let id = query_id();
+        let question = |id, qtype| Question {
+            id,
+            qtype,
+            answer: None,
+        };
+        let query = DnsQuery {
+            socket,
+            nameserver,
+            name: name.to_string(),
+            questions: [
+                question(id, TYPE_A),
+                question(id.wrapping_add(1), TYPE_AAAA),
+            ],
+        };
+        query.resend()?;
+        Ok(query)
+    }
+
+    /// Sends the questions without an answer yet again, same ids
+    pub fn resend(&self) -> Result<(), anyhow::Error> {
+        let server_addr = SockAddr::from(self.nameserver);
+        for question in self.questions.iter().filter(|q| q.answer.is_none()) {
+            let msg = dns_message::query(question.id, &self.name, question.qtype)?;
+            // a full send buffer drops it, the same as the network losing it
+            super::sendto(&self.socket, &msg, &server_addr)?;
+        }
+        Ok(())
+    }
+
+    /// Reads what came in, call it when the socket is readable.
+    /// Some with the A then the AAAA addresses once both answers are in.
+    /// An error means this nameserver is no use for the name: it answered with
+    /// an error rcode, sent a datagram that doesn't parse, or the socket failed.
+    pub fn on_readable(&mut self) -> Result<Option<Vec<IpAddr>>, anyhow::Error> {
+        let mut buf = [0u8; MAX_UDP_RESPONSE];
+        while let Some((n, from)) = super::recvfrom(&self.socket, &mut buf)? {
+            // anyone can send us a datagram, only the nameserver's with our ids count
+            if from.to_std() != Some(self.nameserver) {
+                continue;
+            }
+            let response = dns_message::parse_response(&buf[..n.min(buf.len())])?;
+            let Some(question) = self.questions.iter_mut().find(|q| q.id == response.id) else {
+                continue;
+            };
+            match response.rcode {
+                0 | RCODE_NXDOMAIN => {}
+                rcode => {
+                    return Err(anyhow::anyhow!(
+                        "{} answered {} with rcode {}",
+                        self.nameserver,
+                        self.name,
+                        rcode
+                    ));
+                }
+            }
+            if response.truncated {
+                cyan!("Truncated DNS answer for {}, using what fit", self.name);
+            }
+            question.answer = Some(response.addrs);
+        }
+        let [v4, v6] = &self.questions;
+        match (&v4.answer, &v6.answer) {
+            (Some(v4), Some(v6)) => Ok(Some(v4.iter().chain(v6).copied().collect())),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl AsFd for DnsQuery {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.socket.as_fd()
+    }
+}
+
+/// Every address for `host`, blocking until they are known. IP literals come
+/// back as they are, names in `/etc/hosts` without a query.
+pub fn resolve(host: &str, port: u16, conf: &ResolvConf) -> Result<Vec<SocketAddr>, anyhow::Error> {
+    let epoll = Epoll::new()?;
+    let mut events = Events::with_capacity(1);
+    resolve_on(host, port, conf, &epoll, &mut events, 0, &mut |_| Ok(()))
+}
+
+/// `resolve` in the caller's epoll loop: the queries are registered under `token`
+/// next to whatever else is in there. Events for the other fds go to `on_other`
+/// while we wait, an error from it stops the lookup (Ctrl-C on a signalfd).
+pub fn resolve_on(
+    host: &str,
+    port: u16,
+    conf: &ResolvConf,
+    epoll: &Epoll,
+    events: &mut Events,
+    token: u64,
+    on_other: &mut dyn FnMut(u64) -> Result<(), anyhow::Error>,
+) -> Result<Vec<SocketAddr>, anyhow::Error> {
+    let with_port = |addrs: Vec<IpAddr>| addrs.into_iter().map(|ip| SocketAddr::new(ip, port));
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    let addrs = lookup_hosts(HOSTS_PATH, host)?;
+    if !addrs.is_empty() {
+        cyan!("Found {} in {}", host, HOSTS_PATH);
+        return Ok(with_port(addrs).collect());
+    }
+
+    // a candidate no nameserver answered for is skipped like an NXDOMAIN one,
+    // glibc does the same with a SERVFAIL
+    for name in conf.candidates(host) {
+        match ask_nameservers(&name, conf, epoll, events, token, on_other)? {
+            Some(addrs) if !addrs.is_empty() => {
+                cyan!("Resolved {} to {:?}", name, addrs);
+                return Ok(with_port(addrs).collect());
+            }
+            Some(_) => {}
+            None => cyan!("No nameserver answered for {}", name),
+        }
+    }
+    Err(anyhow::anyhow!("Could not resolve {}", host))
+}
+
+/// Goes through the nameservers `attempts` times until one answers, None when
+/// none did. Only an error from `on_other` or the epoll is returned.
+fn ask_nameservers(
+    name: &str,
+    conf: &ResolvConf,
+    epoll: &Epoll,
+    events: &mut Events,
+    token: u64,
+    on_other: &mut dyn FnMut(u64) -> Result<(), anyhow::Error>,
+) -> Result<Option<Vec<IpAddr>>, anyhow::Error> {
+    for _ in 0..conf.attempts {
+        for &nameserver in &conf.nameservers {
+            // dropping the query closes its socket, which takes it out of the epoll
+            let mut query = match DnsQuery::new(name, nameserver) {
+                Ok(query) => query,
+                Err(err) => {
+                    cyan!("Could not ask {} for {}: {}", nameserver, name, err);
+                    continue;
+                }
+            };
+            epoll.add(&query, token, Interest::READABLE)?;
+            let deadline = Instant::now() + conf.timeout;
+            loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() || epoll.wait(events, Some(left))? == 0 {
+                    cyan!("No answer from {} for {}", nameserver, name);
+                    break;
+                }
+                let mut readable = false;
+                for (event_token, _) in events.iter() {
+                    if event_token == token {
+                        readable = true;
+                    } else {
+                        on_other(event_token)?;
+                    }
+                }
+                if !readable {
+                    continue;
+                }
+                match query.on_readable() {
+                    Ok(Some(addrs)) => return Ok(Some(addrs)),
+                    Ok(None) => {}
+                    Err(err) => {
+                        cyan!("No usable answer from {} for {}: {}", nameserver, name, err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Not random, but different per query, which is enough with the source check
+fn query_id() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.subsec_nanos());
+    (nanos ^ std::process::id()) as u16
+}
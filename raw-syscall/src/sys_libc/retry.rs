@@ -0,0 +1,80 @@
+//! Restarting syscalls interrupted by a signal (EINTR).
+//!
+//! Off by default: a signal handler firing during `poll`, `recv`, etc.
+//! makes the wrapper return an `EINTR` error, just like the C functions do.
+//! Restarting can be turned on for every wrapper with `set_restart_on_eintr`,
+//! or for a single call by wrapping it in `retry`/`retry_with_timeout`.
+use super::SysError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static RESTART_ON_EINTR: AtomicBool = AtomicBool::new(false);
+
+/// Makes every wrapper in `sys_libc` transparently restart calls interrupted by a signal.
+pub fn set_restart_on_eintr(enabled: bool) {
+    RESTART_ON_EINTR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn restart_on_eintr() -> bool {
+    RESTART_ON_EINTR.load(Ordering::Relaxed)
+}
+
+/// Calls `f` again for as long as it fails with EINTR.
+pub fn retry<T>(mut f: impl FnMut() -> Result<T, SysError>) -> Result<T, SysError> {
+    loop {
+        match f() {
+            Err(err) if err.is_interrupted() => {
+                crate::cyan!("{}, restarting", err);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Same as `retry` for calls that take a timeout in milliseconds (negative means forever).
+/// Each restart gets only the time left until the original deadline,
+/// so a burst of signals can't stretch the total wait.
+pub fn retry_with_timeout<T>(
+    timeout: i32,
+    mut f: impl FnMut(i32) -> Result<T, SysError>,
+) -> Result<T, SysError> {
+    let deadline = (timeout >= 0).then(|| Instant::now() + Duration::from_millis(timeout as u64));
+    let mut timeout = timeout;
+    loop {
+        match f(timeout) {
+            Err(err) if err.is_interrupted() => {
+                if let Some(deadline) = deadline {
+                    timeout = remaining_millis(deadline);
+                }
+                crate::cyan!("{}, restarting with timeout {}ms", err, timeout);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Milliseconds left until `deadline`, rounded up so we never wake before it.
+fn remaining_millis(deadline: Instant) -> i32 {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    remaining
+        .as_nanos()
+        .div_ceil(1_000_000)
+        .min(i32::MAX as u128) as i32
+}
+
+/// Used by the wrappers: restarts only when enabled globally.
+pub(crate) fn restartable<T>(mut f: impl FnMut() -> Result<T, SysError>) -> Result<T, SysError> {
+    if restart_on_eintr() { retry(f) } else { f() }
+}
+
+/// Used by the wrappers taking a millisecond timeout: restarts only when enabled globally.
+pub(crate) fn restartable_with_timeout<T>(
+    timeout: i32,
+    mut f: impl FnMut(i32) -> Result<T, SysError>,
+) -> Result<T, SysError> {
+    if restart_on_eintr() {
+        retry_with_timeout(timeout, f)
+    } else {
+        f(timeout)
+    }
+}
@@ -1,48 +1,109 @@
-use super::{FdSet, libc};
+use super::libc::{timespec, timeval};
+use super::retry::restartable;
+use super::{SelectSet, SigSet, SysError, libc};
+use std::time::Duration;
 
 pub fn select_write(
     nfds: i32,
-    writefds: &mut FdSet,
-    timeout: Option<&mut u8>,
-) -> Result<i32, anyhow::Error> {
+    writefds: &mut dyn SelectSet,
+    timeout: Option<Duration>,
+) -> Result<(i32, Option<Duration>), SysError> {
     select(nfds, None, Some(writefds), None, timeout)
 }
 
 pub fn select_read(
     nfds: i32,
-    readfds: &mut FdSet,
-    timeout: Option<&mut u8>,
-) -> Result<i32, anyhow::Error> {
+    readfds: &mut dyn SelectSet,
+    timeout: Option<Duration>,
+) -> Result<(i32, Option<Duration>), SysError> {
     select(nfds, Some(readfds), None, None, timeout)
 }
 
+/// Waits until one of the fds in the sets is ready, or for `timeout` (None waits forever).
+/// Returns the number of ready fds (0 on timeout) and the time that was left,
+/// which Linux writes back into the timeout: pass it to the next call to keep one deadline.
 pub fn select(
     nfds: i32,
-    readfds: Option<&mut FdSet>,
-    writefds: Option<&mut FdSet>,
-    exceptfds: Option<&mut FdSet>,
-    timeout: Option<&mut u8>,
-) -> Result<i32, anyhow::Error> {
-    let readfds = get_mut_ptr(readfds);
-    let writefds = get_mut_ptr(writefds);
-    let exceptfds = get_mut_ptr(exceptfds);
-    let timeout = match timeout {
-        Some(t) => t as *mut u8,
+    readfds: Option<&mut dyn SelectSet>,
+    writefds: Option<&mut dyn SelectSet>,
+    exceptfds: Option<&mut dyn SelectSet>,
+    timeout: Option<Duration>,
+) -> Result<(i32, Option<Duration>), SysError> {
+    let readfds = get_mut_ptr(readfds, nfds)?;
+    let writefds = get_mut_ptr(writefds, nfds)?;
+    let exceptfds = get_mut_ptr(exceptfds, nfds)?;
+    let mut timeout = timeout.map(timeval::from);
+    let timeout_ptr = match timeout.as_mut() {
+        Some(tv) => tv as *mut timeval,
         None => std::ptr::null_mut(),
     };
-    let result = unsafe { libc::select(nfds, readfds, writefds, exceptfds, timeout) };
+    // Linux writes the time left back into the timeout, so restarting
+    // with the same pointer keeps the original deadline.
+    let ready = restartable(|| {
+        let result = unsafe { syscall!(select(nfds, readfds, writefds, exceptfds, timeout_ptr)) };
+        super::stats::count_wakeup();
 
+        if result == -1 {
+            return Err(SysError::last_os_error("select"));
+        }
+
+        Ok(result)
+    })?;
+    Ok((ready, timeout.map(Duration::from)))
+}
+
+/// `select` that swaps in `sigmask` for the duration of the wait, atomically.
+/// Block a signal everywhere else and leave it out of `sigmask`: it can then only
+/// arrive while waiting here, as EINTR, with no race between checking a flag and sleeping.
+/// Because EINTR is the point, this one is never restarted.
+/// Unlike `select`, the timeout is not written back.
+pub fn pselect(
+    nfds: i32,
+    readfds: Option<&mut dyn SelectSet>,
+    writefds: Option<&mut dyn SelectSet>,
+    exceptfds: Option<&mut dyn SelectSet>,
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> Result<i32, SysError> {
+    let timeout = timeout.map(timespec::from);
+    let timeout_ptr = match timeout.as_ref() {
+        Some(ts) => ts as *const timespec,
+        None => std::ptr::null(),
+    };
+    let readfds = get_mut_ptr(readfds, nfds)?;
+    let writefds = get_mut_ptr(writefds, nfds)?;
+    let exceptfds = get_mut_ptr(exceptfds, nfds)?;
+    let sigmask = match sigmask {
+        Some(sigmask) => sigmask.as_ptr(),
+        None => std::ptr::null(),
+    };
+    let result = unsafe {
+        syscall!(pselect(
+            nfds,
+            readfds,
+            writefds,
+            exceptfds,
+            timeout_ptr,
+            sigmask
+        ))
+    };
+    super::stats::count_wakeup();
     if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("Select failed: {}", errno));
+        return Err(SysError::last_os_error("pselect"));
     }
-
     Ok(result)
 }
 
-fn get_mut_ptr(fd_set: Option<&mut FdSet>) -> *mut libc::fd_set {
+/// Also checks the set is big enough for the kernel to touch `nfds` bits
+fn get_mut_ptr(
+    fd_set: Option<&mut dyn SelectSet>,
+    nfds: i32,
+) -> Result<*mut libc::fd_set, SysError> {
     match fd_set {
-        Some(fd_set) => fd_set.as_mut_ptr(),
-        None => std::ptr::null_mut(),
+        Some(fd_set) => {
+            fd_set.reserve(nfds)?;
+            Ok(fd_set.as_mut_ptr())
+        }
+        None => Ok(std::ptr::null_mut()),
     }
 }
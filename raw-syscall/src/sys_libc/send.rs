@@ -1,18 +1,24 @@
-use super::{SocketFd, libc};
+use super::retry::restartable;
+use super::{SocketFd, SysError};
 use crate::cyan;
 
-pub fn send(sockfd: &SocketFd, buf: &[u8]) -> Result<Option<usize>, anyhow::Error> {
-    let bytes_sent = unsafe { libc::send(sockfd.0, buf.as_ptr(), buf.len(), 0) };
-    if bytes_sent == -1 {
-        let errno = std::io::Error::last_os_error();
-        if let Some(os_error) = errno.raw_os_error() {
-            if os_error == libc::EWOULDBLOCK || os_error == libc::EAGAIN {
-                cyan!("Would block, cannot send data on {}, ({})", sockfd, errno);
-                return Ok(None); // Would block, cannot send data
-            }
+pub fn send(sockfd: &SocketFd, buf: &[u8]) -> Result<Option<usize>, SysError> {
+    let bytes_sent = restartable(|| {
+        let bytes_sent = unsafe { syscall!(send(sockfd.0, buf.as_ptr(), buf.len(), 0)) };
+        if bytes_sent == -1 {
+            return Err(SysError::last_os_error("send"));
+        }
+        Ok(bytes_sent as usize)
+    });
+    match bytes_sent {
+        Err(err) if err.is_would_block() => {
+            cyan!("Would block, cannot send data on {}, ({})", sockfd, err);
+            Ok(None) // Would block, cannot send data
+        }
+        Err(err) => Err(err),
+        Ok(bytes_sent) => {
+            cyan!("Sent {} bytes", bytes_sent);
+            Ok(Some(bytes_sent))
         }
-        return Err(anyhow::anyhow!("Failed to send data: {}", errno));
     }
-    cyan!("Sent {} bytes", bytes_sent);
-    Ok(Some(bytes_sent as usize))
 }
@@ -0,0 +1,31 @@
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+use std::ptr;
+
+/// Copies up to `count` bytes from `in_fd` (a file, something mmap-able) to `out_fd`
+/// inside the kernel, the data never comes up to user space.
+/// With an `offset` the file position is left alone and the offset is moved instead.
+/// Ok(None) when `out_fd` would block, Ok(Some(0)) at end of file.
+pub fn sendfile<'a, 'b>(
+    out_fd: impl Into<SocketRef<'a>>,
+    in_fd: impl Into<SocketRef<'b>>,
+    mut offset: Option<&mut i64>,
+    count: usize,
+) -> Result<Option<usize>, SysError> {
+    let (out_fd, in_fd) = (out_fd.into(), in_fd.into());
+    let bytes_sent = restartable(|| {
+        let offset = offset
+            .as_deref_mut()
+            .map_or(ptr::null_mut(), |o| o as *mut i64);
+        let bytes_sent = unsafe { syscall!(sendfile(out_fd.0, in_fd.0, offset, count)) };
+        if bytes_sent == -1 {
+            return Err(SysError::last_os_error("sendfile"));
+        }
+        Ok(bytes_sent as usize)
+    });
+    match bytes_sent {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_sent) => Ok(Some(bytes_sent)),
+    }
+}
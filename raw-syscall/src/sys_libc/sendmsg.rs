@@ -0,0 +1,58 @@
+use super::cmsg::{ControlBuffer, ControlMessage};
+use super::libc::{iovec, msghdr};
+use super::retry::restartable;
+use super::{SockAddr, SocketFd, SysError};
+use crate::cyan;
+use std::io::IoSlice;
+
+/// `send` from several buffers at once, with control messages next to the data.
+/// `to` is for unconnected datagram sockets, None on connected ones.
+/// If it would block, returns Ok(None).
+pub fn sendmsg(
+    sockfd: &SocketFd,
+    bufs: &[IoSlice],
+    to: Option<&SockAddr>,
+    control: &[ControlMessage],
+) -> Result<Option<usize>, SysError> {
+    let mut control = ControlBuffer::encode(control);
+    let (name, namelen) = match to {
+        Some(addr) => (addr.as_ptr() as *mut _, addr.len()),
+        None => (std::ptr::null_mut(), 0),
+    };
+    let msg = msghdr {
+        msg_name: name,
+        msg_namelen: namelen,
+        // IoSlice is guaranteed to be an iovec on unix
+        msg_iov: bufs.as_ptr() as *mut iovec,
+        msg_iovlen: bufs.len(),
+        msg_control: match control.len() {
+            0 => std::ptr::null_mut(),
+            _ => control.as_mut_ptr() as *mut _,
+        },
+        msg_controllen: control.len(),
+        msg_flags: 0,
+    };
+    let bytes_sent = restartable(|| {
+        let bytes_sent = unsafe { syscall!(sendmsg(sockfd.0, &msg, 0)) };
+        if bytes_sent == -1 {
+            return Err(SysError::last_os_error("sendmsg"));
+        }
+        Ok(bytes_sent as usize)
+    });
+    match bytes_sent {
+        Err(err) if err.is_would_block() => {
+            cyan!("Would block, cannot send data on {}, ({})", sockfd, err);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+        Ok(bytes_sent) => {
+            cyan!(
+                "Sent {} bytes from {} buffers with {} bytes of control data",
+                bytes_sent,
+                bufs.len(),
+                control.len()
+            );
+            Ok(Some(bytes_sent))
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use super::retry::restartable;
+use super::{SockAddr, SocketFd, SysError};
+use crate::cyan;
+
+/// Sends `buf` as a single datagram to `addr`.
+/// The datagram goes out whole or not at all: too big for the protocol fails with EMSGSIZE.
+/// If the send buffer is full on a non-blocking socket, returns Ok(None).
+pub fn sendto(sockfd: &SocketFd, buf: &[u8], addr: &SockAddr) -> Result<Option<usize>, SysError> {
+    let bytes_sent = restartable(|| {
+        let bytes_sent = unsafe {
+            syscall!(sendto(
+                sockfd.0,
+                buf.as_ptr(),
+                buf.len(),
+                0,
+                addr.as_ptr(),
+                addr.len(),
+            ))
+        };
+        if bytes_sent == -1 {
+            return Err(SysError::last_os_error("sendto"));
+        }
+        Ok(bytes_sent as usize)
+    });
+    match bytes_sent {
+        Err(err) if err.is_would_block() => {
+            cyan!("Would block, cannot send datagram on {}, ({})", sockfd, err);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+        Ok(bytes_sent) => {
+            cyan!("Sent {} bytes to {}", bytes_sent, addr);
+            Ok(Some(bytes_sent))
+        }
+    }
+}
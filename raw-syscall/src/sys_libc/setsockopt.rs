@@ -0,0 +1,23 @@
+use super::{SocketFd, SysError};
+
+pub fn setsockopt(
+    sockfd: &SocketFd,
+    level: i32,
+    optname: i32,
+    optval: &[u8],
+) -> Result<(), SysError> {
+    let optlen = optval.len() as u32;
+    let ret = unsafe {
+        syscall!(setsockopt(
+            sockfd.0,
+            level,
+            optname,
+            optval.as_ptr(),
+            optlen
+        ))
+    };
+    if ret == -1 {
+        return Err(SysError::last_os_error("setsockopt"));
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Signal set passed as the mask of `pselect` and friends.
+//! Built in Rust instead of calling `sigemptyset`/`sigaddset`, it is just a bitmap.
+use super::libc::{
+    SIGALRM, SIGCHLD, SIGHUP, SIGINT, SIGPIPE, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2, sigset_t,
+};
+use std::fmt::Display;
+
+#[derive(Clone, Copy)]
+pub struct SigSet(pub(crate) sigset_t);
+
+impl SigSet {
+    pub fn empty() -> Self {
+        SigSet(sigset_t { __val: [0; 16] })
+    }
+
+    /// Every signal, the kernel silently ignores SIGKILL and SIGSTOP in masks
+    pub fn full() -> Self {
+        SigSet(sigset_t {
+            __val: [u64::MAX; 16],
+        })
+    }
+
+    pub fn add(&mut self, signal: i32) -> &mut Self {
+        let (idx, bit) = Self::idx_bit(signal);
+        self.0.__val[idx] |= bit;
+        self
+    }
+
+    pub fn remove(&mut self, signal: i32) -> &mut Self {
+        let (idx, bit) = Self::idx_bit(signal);
+        self.0.__val[idx] &= !bit;
+        self
+    }
+
+    pub fn contains(&self, signal: i32) -> bool {
+        let (idx, bit) = Self::idx_bit(signal);
+        self.0.__val[idx] & bit != 0
+    }
+
+    /// Signals start at 1, so signal n is bit n - 1
+    fn idx_bit(signal: i32) -> (usize, u64) {
+        assert!((1..=1024).contains(&signal), "invalid signal {}", signal);
+        let bit = (signal - 1) as usize;
+        (bit / 64, 1u64 << (bit % 64))
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const sigset_t {
+        &self.0
+    }
+}
+
+/// Name of the signals in `libc.rs`, "signal" for the others
+pub fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        SIGHUP => "SIGHUP",
+        SIGINT => "SIGINT",
+        SIGQUIT => "SIGQUIT",
+        SIGUSR1 => "SIGUSR1",
+        SIGUSR2 => "SIGUSR2",
+        SIGPIPE => "SIGPIPE",
+        SIGALRM => "SIGALRM",
+        SIGTERM => "SIGTERM",
+        SIGCHLD => "SIGCHLD",
+        _ => "signal",
+    }
+}
+
+impl Default for SigSet {
+    fn default() -> Self {
+        SigSet::empty()
+    }
+}
+
+impl Display for SigSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let signals: Vec<i32> = (1..=64).filter(|&signal| self.contains(signal)).collect();
+        write!(f, "SigSet({:?})", signals)
+    }
+}
@@ -0,0 +1,34 @@
+//! Signalfd wrapper: blocked signals queue up on the fd instead of running a
+//! handler, so an event loop can wait for them next to its sockets and handle
+//! them between two events, not in the middle of one. Closes the fd when dropped.
+use super::libc::{SFD_CLOEXEC, SFD_NONBLOCK, signalfd_siginfo};
+use super::{SigSet, SysError, read, signalfd};
+use crate::cyan;
+use std::mem;
+
+pub struct SignalFd(pub(crate) i32);
+
+impl SignalFd {
+    /// Non-blocking, for the signals in `mask`. Block them first (`sigprocmask`)
+    /// or their default action still runs, and for SIGINT that is exiting.
+    pub fn new(mask: &SigSet) -> Result<Self, SysError> {
+        let fd = signalfd(mask, SFD_NONBLOCK | SFD_CLOEXEC)?;
+        cyan!("Created signalfd {} for {}", fd, mask);
+        Ok(fd)
+    }
+
+    /// Takes the next pending signal off the queue, None if there is none
+    pub fn read(&self) -> Result<Option<i32>, SysError> {
+        let mut buf = [0u8; mem::size_of::<signalfd_siginfo>()];
+        match read(self, &mut buf)? {
+            // the kernel only ever hands out whole records
+            Some(n) if n == buf.len() => {
+                let info = unsafe { (buf.as_ptr() as *const signalfd_siginfo).read_unaligned() };
+                Ok(Some(info.ssi_signo as i32))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+owned_fd_type!(SignalFd, "an open signalfd", "Signalfd closed");
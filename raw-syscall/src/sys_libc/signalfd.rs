@@ -0,0 +1,11 @@
+use super::{SigSet, SignalFd, SysError};
+
+/// A new fd for the signals in `mask`, `flags` is any of `SFD_NONBLOCK` and `SFD_CLOEXEC`.
+/// They still get their normal handling unless blocked, see `sigprocmask`.
+pub fn signalfd(mask: &SigSet, flags: i32) -> Result<SignalFd, SysError> {
+    let fd = unsafe { syscall!(signalfd(-1, mask.as_ptr(), flags)) };
+    if fd == -1 {
+        return Err(SysError::last_os_error("signalfd"));
+    }
+    Ok(SignalFd(fd))
+}
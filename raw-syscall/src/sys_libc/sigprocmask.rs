@@ -0,0 +1,17 @@
+use super::{SigSet, SysError};
+
+/// Changes the calling thread's signal mask: `how` is `SIG_BLOCK`, `SIG_UNBLOCK`
+/// or `SIG_SETMASK`, None only reads it. Returns the mask as it was before.
+/// Threads start with their parent's mask, so block before spawning any.
+pub fn sigprocmask(how: i32, set: Option<&SigSet>) -> Result<SigSet, SysError> {
+    let set = match set {
+        Some(set) => set.as_ptr(),
+        None => std::ptr::null(),
+    };
+    let mut old = SigSet::empty();
+    let result = unsafe { syscall!(sigprocmask(how, set, &mut old.0)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("sigprocmask"));
+    }
+    Ok(old)
+}
@@ -0,0 +1,165 @@
+//! Socket address for the families we can connect to.
+//! Keeps the C struct so it can be handed to the kernel as a `sockaddr` pointer.
+use super::libc::{
+    AF_INET, AF_INET6, AF_UNIX, sockaddr, sockaddr_in, sockaddr_in6, sockaddr_storage, sockaddr_un,
+};
+use super::{Errno, SysError};
+use std::fmt::Display;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Offset of `sun_path` in `sockaddr_un`, a length this short is an unnamed socket
+const SUN_PATH_OFFSET: u32 = mem::size_of::<u16>() as u32;
+
+#[derive(Clone, Copy)]
+pub enum SockAddr {
+    V4(sockaddr_in),
+    V6(sockaddr_in6),
+    /// The length is part of the address: abstract names are not NUL terminated
+    Unix(sockaddr_un, u32),
+}
+
+impl SockAddr {
+    /// Unix socket bound to a path in the filesystem
+    pub fn unix(path: impl AsRef<Path>) -> Result<SockAddr, SysError> {
+        let path = path.as_ref().as_os_str().as_bytes();
+        if path.contains(&0) {
+            return Err(SysError::new("sockaddr_un", Errno::EINVAL.raw()));
+        }
+        // room for the terminating NUL
+        Self::unix_from_bytes(path, path.len() as u32 + 1)
+    }
+
+    /// Unix socket in the abstract namespace (Linux only): no file is created
+    /// and the name goes away with the last socket using it. Shown as `@name`.
+    pub fn unix_abstract(name: &[u8]) -> Result<SockAddr, SysError> {
+        let mut path = vec![0];
+        path.extend_from_slice(name);
+        Self::unix_from_bytes(&path, path.len() as u32)
+    }
+
+    fn unix_from_bytes(path: &[u8], path_len: u32) -> Result<SockAddr, SysError> {
+        let mut addr = sockaddr_un {
+            sun_family: AF_UNIX as u16,
+            sun_path: [0; 108],
+        };
+        if path_len as usize > addr.sun_path.len() {
+            return Err(SysError::new("sockaddr_un", Errno::ENAMETOOLONG.raw()));
+        }
+        addr.sun_path[..path.len()].copy_from_slice(path);
+        Ok(SockAddr::Unix(addr, SUN_PATH_OFFSET + path_len))
+    }
+
+    /// Address family to pass to `socket(2)`
+    pub fn family(&self) -> i32 {
+        match self {
+            SockAddr::V4(_) => AF_INET,
+            SockAddr::V6(_) => AF_INET6,
+            SockAddr::Unix(..) => AF_UNIX,
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const sockaddr {
+        match self {
+            SockAddr::V4(addr) => addr as *const sockaddr_in as *const sockaddr,
+            SockAddr::V6(addr) => addr as *const sockaddr_in6 as *const sockaddr,
+            SockAddr::Unix(addr, _) => addr as *const sockaddr_un as *const sockaddr,
+        }
+    }
+
+    pub(crate) fn len(&self) -> u32 {
+        match self {
+            SockAddr::V4(_) => mem::size_of::<sockaddr_in>() as u32,
+            SockAddr::V6(_) => mem::size_of::<sockaddr_in6>() as u32,
+            SockAddr::Unix(_, len) => *len,
+        }
+    }
+
+    /// Reads back an address the kernel wrote into a `sockaddr_storage`
+    /// (`accept4`, `getsockname`, ...). `len` is the length the kernel reported.
+    /// Returns None for families we don't handle.
+    pub(crate) fn from_storage(storage: &sockaddr_storage, len: u32) -> Option<SockAddr> {
+        let ptr = storage as *const sockaddr_storage;
+        match storage.ss_family as i32 {
+            AF_INET => Some(SockAddr::V4(unsafe { *(ptr as *const sockaddr_in) })),
+            AF_INET6 => Some(SockAddr::V6(unsafe { *(ptr as *const sockaddr_in6) })),
+            // unnamed peers (unbound clients, socketpair) come back with just the family
+            AF_UNIX => Some(SockAddr::Unix(
+                unsafe { *(ptr as *const sockaddr_un) },
+                len.clamp(SUN_PATH_OFFSET, mem::size_of::<sockaddr_un>() as u32),
+            )),
+            _ => None,
+        }
+    }
+
+    /// None for Unix sockets, std has no `SocketAddr` for them
+    pub fn to_std(self) -> Option<SocketAddr> {
+        match self {
+            SockAddr::V4(addr) => Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(addr.sin_addr.to_ne_bytes()),
+                u16::from_be(addr.sin_port),
+            ))),
+            SockAddr::V6(addr) => Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr),
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            ))),
+            SockAddr::Unix(..) => None,
+        }
+    }
+}
+
+impl From<SocketAddr> for SockAddr {
+    fn from(addr: SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(addr) => SockAddr::V4(sockaddr_in {
+                sin_family: AF_INET as u16,
+                sin_port: addr.port().to_be(),
+                sin_addr: u32::from_ne_bytes(addr.ip().octets()),
+                sin_zero: [0; 8],
+            }),
+            SocketAddr::V6(addr) => SockAddr::V6(sockaddr_in6 {
+                sin6_family: AF_INET6 as u16,
+                sin6_port: addr.port().to_be(),
+                // like std, flowinfo and scope id are passed through untouched
+                sin6_flowinfo: addr.flowinfo(),
+                sin6_addr: addr.ip().octets(),
+                sin6_scope_id: addr.scope_id(),
+            }),
+        }
+    }
+}
+
+impl From<sockaddr_in> for SockAddr {
+    fn from(addr: sockaddr_in) -> Self {
+        SockAddr::V4(addr)
+    }
+}
+
+impl From<sockaddr_in6> for SockAddr {
+    fn from(addr: sockaddr_in6) -> Self {
+        SockAddr::V6(addr)
+    }
+}
+
+impl Display for SockAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SockAddr::Unix(addr, len) => {
+                let path = &addr.sun_path[..(len - SUN_PATH_OFFSET) as usize];
+                match path {
+                    [] => write!(f, "(unnamed)"),
+                    [0, name @ ..] => write!(f, "@{}", String::from_utf8_lossy(name)),
+                    _ => {
+                        let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                        write!(f, "{}", String::from_utf8_lossy(&path[..end]))
+                    }
+                }
+            }
+            _ => write!(f, "{}", self.to_std().unwrap()),
+        }
+    }
+}
@@ -1,33 +1,77 @@
 //! wrapper functions that interact with `socket(2)`
-use super::{SocketFd, libc};
+use super::libc::AF_UNIX;
+use super::{SocketFd, SysError};
 use crate::cyan;
-use libc::AF_INET;
 
 pub const SOCK_STREAM: i32 = 1;
+pub const SOCK_DGRAM: i32 = 2;
 pub const NON_BLOCKING: i32 = 0o4000;
+pub const SOCK_NONBLOCK: i32 = NON_BLOCKING;
+pub const SOCK_CLOEXEC: i32 = 0o2000000;
 pub const IPPROTO_TCP: i32 = 6;
+pub const IPPROTO_UDP: i32 = 17;
 
-pub fn create_tcp_socket() -> Result<SocketFd, anyhow::Error> {
-    let sockfd = unsafe { libc::socket(AF_INET, SOCK_STREAM, IPPROTO_TCP) };
+/// `family` is `AF_INET` or `AF_INET6`, usually taken from `SockAddr::family`
+pub fn create_tcp_socket(family: i32) -> Result<SocketFd, SysError> {
+    let sockfd = unsafe { syscall!(socket(family, SOCK_STREAM, IPPROTO_TCP)) };
     if sockfd == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!("Failed to create socket: {}", errno));
+        return Err(SysError::last_os_error("socket"));
     }
     let fd = SocketFd(sockfd);
     cyan!("Created socket: {}", fd);
     Ok(fd)
 }
 
-pub fn create_non_blocking_tcp_socket() -> Result<SocketFd, anyhow::Error> {
-    let sockfd = unsafe { libc::socket(AF_INET, SOCK_STREAM | NON_BLOCKING, IPPROTO_TCP) };
+pub fn create_non_blocking_tcp_socket(family: i32) -> Result<SocketFd, SysError> {
+    let sockfd = unsafe { syscall!(socket(family, SOCK_STREAM | NON_BLOCKING, IPPROTO_TCP)) };
     if sockfd == -1 {
-        let errno = std::io::Error::last_os_error();
-        return Err(anyhow::anyhow!(
-            "Failed to create non-blocking socket: {}",
-            errno
-        ));
+        return Err(SysError::last_os_error("socket"));
     }
     let fd = SocketFd(sockfd);
     crate::cyan!("Created non-blocking socket: {}", fd);
     Ok(fd)
 }
+
+/// UDP socket: no connection, every `sendto` is one datagram
+pub fn create_udp_socket(family: i32) -> Result<SocketFd, SysError> {
+    let sockfd = unsafe { syscall!(socket(family, SOCK_DGRAM, IPPROTO_UDP)) };
+    if sockfd == -1 {
+        return Err(SysError::last_os_error("socket"));
+    }
+    let fd = SocketFd(sockfd);
+    cyan!("Created UDP socket: {}", fd);
+    Ok(fd)
+}
+
+pub fn create_non_blocking_udp_socket(family: i32) -> Result<SocketFd, SysError> {
+    let sockfd = unsafe { syscall!(socket(family, SOCK_DGRAM | NON_BLOCKING, IPPROTO_UDP)) };
+    if sockfd == -1 {
+        return Err(SysError::last_os_error("socket"));
+    }
+    let fd = SocketFd(sockfd);
+    cyan!("Created non-blocking UDP socket: {}", fd);
+    Ok(fd)
+}
+
+/// Unix domain stream socket, `flags` is 0 or `SOCK_NONBLOCK | SOCK_CLOEXEC`
+pub fn create_unix_stream_socket(flags: i32) -> Result<SocketFd, SysError> {
+    // unix sockets have a single protocol, 0
+    let sockfd = unsafe { syscall!(socket(AF_UNIX, SOCK_STREAM | flags, 0)) };
+    if sockfd == -1 {
+        return Err(SysError::last_os_error("socket"));
+    }
+    let fd = SocketFd(sockfd);
+    cyan!("Created unix stream socket: {}", fd);
+    Ok(fd)
+}
+
+/// Unix domain datagram socket: message boundaries like UDP, but reliable and ordered
+pub fn create_unix_datagram_socket(flags: i32) -> Result<SocketFd, SysError> {
+    let sockfd = unsafe { syscall!(socket(AF_UNIX, SOCK_DGRAM | flags, 0)) };
+    if sockfd == -1 {
+        return Err(SysError::last_os_error("socket"));
+    }
+    let fd = SocketFd(sockfd);
+    cyan!("Created unix datagram socket: {}", fd);
+    Ok(fd)
+}
@@ -1,8 +1,5 @@
 //! Socket file descriptor wrapper
-//! Closes the socket when droppeduse super::libc;
-use super::libc;
-use crate::cyan;
-use std::fmt::Display;
+//! Closes the socket when dropped
 
 pub struct SocketFd(pub(crate) i32);
 
@@ -18,14 +15,4 @@ impl PartialOrd for SocketFd {
     }
 }
 
-impl Drop for SocketFd {
-    fn drop(&mut self) {
-        unsafe { libc::close(self.0) };
-        cyan!("Socket closed {}", self);
-    }
-}
-impl Display for SocketFd {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "SocketFd({})", self.0)
-    }
-}
+owned_fd_type!(SocketFd, "an open socket", "Socket closed");
@@ -0,0 +1,41 @@
+//! Borrowed socket file descriptor.
+//! Lets the multiplexing wrappers (epoll, poll, select) take any socket that lends out its fd: our `SocketFd`,
+//! std's `TcpStream`, mio's `TcpStream`, ... without taking ownership.
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+/// Like `BorrowedFd`, the fd stays open for as long as `'a`
+#[derive(Clone, Copy)]
+pub struct SocketRef<'a>(pub(crate) i32, PhantomData<BorrowedFd<'a>>);
+
+impl<'a, T: AsFd> From<&'a T> for SocketRef<'a> {
+    fn from(socket: &'a T) -> Self {
+        SocketRef(socket.as_fd().as_raw_fd(), PhantomData)
+    }
+}
+
+impl<'a> From<BorrowedFd<'a>> for SocketRef<'a> {
+    fn from(fd: BorrowedFd<'a>) -> Self {
+        SocketRef(fd.as_raw_fd(), PhantomData)
+    }
+}
+
+impl AsFd for SocketRef<'_> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the lifetime guarantees the owner hasn't closed the fd yet
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+impl AsRawFd for SocketRef<'_> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Display for SocketRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SocketRef({})", self.0)
+    }
+}
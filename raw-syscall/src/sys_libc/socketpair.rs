@@ -0,0 +1,15 @@
+use super::{SocketFd, SysError};
+use crate::cyan;
+
+/// Two sockets connected to each other, `domain` is `AF_UNIX` on Linux.
+/// `type_` is `SOCK_STREAM` or `SOCK_DGRAM`, optionally with `SOCK_NONBLOCK | SOCK_CLOEXEC`.
+pub fn socketpair(domain: i32, type_: i32) -> Result<(SocketFd, SocketFd), SysError> {
+    let mut fds = [0; 2];
+    let result = unsafe { syscall!(socketpair(domain, type_, 0, &mut fds)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("socketpair"));
+    }
+    let pair = (SocketFd(fds[0]), SocketFd(fds[1]));
+    cyan!("Created socket pair: {} and {}", pair.0, pair.1);
+    Ok(pair)
+}
@@ -0,0 +1,32 @@
+//! wrappers around `getsockname(2)` and `getpeername(2)`
+use super::libc::{sockaddr, sockaddr_storage};
+use super::{Errno, SockAddr, SocketFd, SysError};
+use std::mem;
+
+/// Local address the socket is bound to (useful after binding to port 0)
+pub fn getsockname(sockfd: &SocketFd) -> Result<SockAddr, SysError> {
+    get_name("getsockname", |addr, len| unsafe {
+        syscall!(getsockname(sockfd.0, addr, len))
+    })
+}
+
+/// Address of the peer a connected socket talks to
+pub fn getpeername(sockfd: &SocketFd) -> Result<SockAddr, SysError> {
+    get_name("getpeername", |addr, len| unsafe {
+        syscall!(getpeername(sockfd.0, addr, len))
+    })
+}
+
+fn get_name(
+    syscall: &'static str,
+    f: impl FnOnce(*mut sockaddr, *mut u32) -> i32,
+) -> Result<SockAddr, SysError> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<sockaddr_storage>() as u32;
+    let addr = &mut storage as *mut sockaddr_storage as *mut sockaddr;
+    let result = f(addr, &mut len);
+    if result == -1 {
+        return Err(SysError::last_os_error(syscall));
+    }
+    SockAddr::from_storage(&storage, len).ok_or(SysError::new(syscall, Errno::EAFNOSUPPORT.raw()))
+}
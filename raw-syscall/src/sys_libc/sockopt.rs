@@ -0,0 +1,203 @@
+//! Typed socket options on top of `setsockopt`/`getsockopt`.
+//!
+//! Each option is a unit struct knowing its level, name and the C type the
+//! kernel expects, so callers write `set_opt(&socket, TcpNoDelay, true)`
+//! instead of packing bytes by hand.
+use super::libc::{self, linger, timeval};
+use super::socket::IPPROTO_TCP;
+use super::{SocketFd, SysError, getsockopt, setsockopt};
+use std::time::Duration;
+use std::{mem, slice};
+
+pub trait SockOpt {
+    /// Rust side value
+    type Value;
+    /// What the kernel reads and writes, must be a plain C type (int or struct of ints)
+    type Raw: Copy;
+    const LEVEL: i32;
+    const NAME: i32;
+
+    fn to_raw(value: Self::Value) -> Self::Raw;
+    fn from_raw(raw: Self::Raw) -> Self::Value;
+}
+
+pub fn set_opt<O: SockOpt>(sockfd: &SocketFd, _opt: O, value: O::Value) -> Result<(), SysError> {
+    let raw = O::to_raw(value);
+    let bytes = unsafe {
+        slice::from_raw_parts(&raw as *const O::Raw as *const u8, mem::size_of::<O::Raw>())
+    };
+    setsockopt(sockfd, O::LEVEL, O::NAME, bytes)
+}
+
+pub fn get_opt<O: SockOpt>(sockfd: &SocketFd, _opt: O) -> Result<O::Value, SysError> {
+    let mut raw: O::Raw = unsafe { mem::zeroed() };
+    let bytes = unsafe {
+        slice::from_raw_parts_mut(&mut raw as *mut O::Raw as *mut u8, mem::size_of::<O::Raw>())
+    };
+    getsockopt(sockfd, O::LEVEL, O::NAME, bytes)?;
+    Ok(O::from_raw(raw))
+}
+
+/// Declares an on/off option stored by the kernel as an int
+macro_rules! bool_opt {
+    ($(#[$doc:meta])* $name:ident, $level:expr, $optname:expr) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl SockOpt for $name {
+            type Value = bool;
+            type Raw = i32;
+            const LEVEL: i32 = $level;
+            const NAME: i32 = $optname;
+
+            fn to_raw(value: bool) -> i32 {
+                value as i32
+            }
+
+            fn from_raw(raw: i32) -> bool {
+                raw != 0
+            }
+        }
+    };
+}
+
+/// Declares a size option stored by the kernel as an int
+macro_rules! size_opt {
+    ($(#[$doc:meta])* $name:ident, $level:expr, $optname:expr) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl SockOpt for $name {
+            type Value = usize;
+            type Raw = i32;
+            const LEVEL: i32 = $level;
+            const NAME: i32 = $optname;
+
+            fn to_raw(value: usize) -> i32 {
+                value.min(i32::MAX as usize) as i32
+            }
+
+            fn from_raw(raw: i32) -> usize {
+                raw as usize
+            }
+        }
+    };
+}
+
+bool_opt!(
+    /// Lets a listener bind a port still holding connections in TIME_WAIT
+    ReuseAddr,
+    libc::SOL_SOCKET,
+    libc::SO_REUSEADDR
+);
+bool_opt!(
+    /// Lets several sockets bind the same address, the kernel balances between them
+    ReusePort,
+    libc::SOL_SOCKET,
+    libc::SO_REUSEPORT
+);
+bool_opt!(
+    /// Sends keepalive probes on idle connections
+    KeepAlive,
+    libc::SOL_SOCKET,
+    libc::SO_KEEPALIVE
+);
+bool_opt!(
+    /// Disables Nagle's algorithm, small writes go out immediately
+    TcpNoDelay,
+    IPPROTO_TCP,
+    libc::TCP_NODELAY
+);
+bool_opt!(
+    /// Attaches the time the kernel received the data to `recvmsg` as `SCM_TIMESTAMP`
+    Timestamp,
+    libc::SOL_SOCKET,
+    libc::SO_TIMESTAMP
+);
+size_opt!(
+    /// Receive buffer size, the kernel doubles what is set and reports the doubled value
+    RcvBuf,
+    libc::SOL_SOCKET,
+    libc::SO_RCVBUF
+);
+size_opt!(
+    /// Send buffer size, the kernel doubles what is set and reports the doubled value
+    SndBuf,
+    libc::SOL_SOCKET,
+    libc::SO_SNDBUF
+);
+
+/// How long `close` blocks to flush unsent data, None restores the default background flush
+pub struct Linger;
+
+impl SockOpt for Linger {
+    type Value = Option<Duration>;
+    type Raw = linger;
+    const LEVEL: i32 = libc::SOL_SOCKET;
+    const NAME: i32 = libc::SO_LINGER;
+
+    fn to_raw(value: Option<Duration>) -> linger {
+        match value {
+            Some(duration) => linger {
+                l_onoff: 1,
+                l_linger: duration.as_secs().min(i32::MAX as u64) as i32,
+            },
+            None => linger {
+                l_onoff: 0,
+                l_linger: 0,
+            },
+        }
+    }
+
+    fn from_raw(raw: linger) -> Option<Duration> {
+        (raw.l_onoff != 0).then(|| Duration::from_secs(raw.l_linger as u64))
+    }
+}
+
+/// Idle time before the first keepalive probe (whole seconds)
+pub struct TcpKeepIdle;
+
+impl SockOpt for TcpKeepIdle {
+    type Value = Duration;
+    type Raw = i32;
+    const LEVEL: i32 = IPPROTO_TCP;
+    const NAME: i32 = libc::TCP_KEEPIDLE;
+
+    fn to_raw(value: Duration) -> i32 {
+        value.as_secs().clamp(1, i32::MAX as u64) as i32
+    }
+
+    fn from_raw(raw: i32) -> Duration {
+        Duration::from_secs(raw as u64)
+    }
+}
+
+/// Timeout for blocking receives, they fail with EAGAIN once it expires.
+/// None (a zero timeval) blocks forever.
+pub struct RcvTimeo;
+
+impl SockOpt for RcvTimeo {
+    type Value = Option<Duration>;
+    type Raw = timeval;
+    const LEVEL: i32 = libc::SOL_SOCKET;
+    const NAME: i32 = libc::SO_RCVTIMEO;
+
+    fn to_raw(value: Option<Duration>) -> timeval {
+        let Some(value) = value else {
+            return timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            };
+        };
+        timeval {
+            tv_sec: value.as_secs() as i64,
+            // don't let a sub-microsecond timeout turn into "forever"
+            tv_usec: (value.subsec_micros() as i64).max((value.as_secs() == 0) as i64),
+        }
+    }
+
+    fn from_raw(raw: timeval) -> Option<Duration> {
+        let value = Duration::new(raw.tv_sec as u64, raw.tv_usec as u32 * 1000);
+        (!value.is_zero()).then_some(value)
+    }
+}
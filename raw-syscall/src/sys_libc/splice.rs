@@ -0,0 +1,62 @@
+//! Moving data between fds through a pipe's buffer, without copying it to user space.
+//! One side of a `splice` has to be a pipe, both sides of a `tee`.
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+use std::ptr;
+
+/// Moves up to `len` bytes from `fd_in` to `fd_out`. The offsets are only for the
+/// side that isn't the pipe and, like `sendfile`'s, leave the file position alone.
+/// `flags` is any of `SPLICE_F_MOVE`, `SPLICE_F_NONBLOCK` and `SPLICE_F_MORE`.
+/// Ok(None) when it would block, Ok(Some(0)) when `fd_in` is at its end.
+pub fn splice<'a, 'b>(
+    fd_in: impl Into<SocketRef<'a>>,
+    mut off_in: Option<&mut i64>,
+    fd_out: impl Into<SocketRef<'b>>,
+    mut off_out: Option<&mut i64>,
+    len: usize,
+    flags: u32,
+) -> Result<Option<usize>, SysError> {
+    let (fd_in, fd_out) = (fd_in.into(), fd_out.into());
+    let bytes_moved = restartable(|| {
+        let off_in = off_in
+            .as_deref_mut()
+            .map_or(ptr::null_mut(), |o| o as *mut i64);
+        let off_out = off_out
+            .as_deref_mut()
+            .map_or(ptr::null_mut(), |o| o as *mut i64);
+        let bytes_moved =
+            unsafe { syscall!(splice(fd_in.0, off_in, fd_out.0, off_out, len, flags)) };
+        if bytes_moved == -1 {
+            return Err(SysError::last_os_error("splice"));
+        }
+        Ok(bytes_moved as usize)
+    });
+    match bytes_moved {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_moved) => Ok(Some(bytes_moved)),
+    }
+}
+
+/// Duplicates up to `len` bytes from one pipe into another without consuming them,
+/// `fd_in` still holds the data for a `read` or `splice` afterwards.
+pub fn tee<'a, 'b>(
+    fd_in: impl Into<SocketRef<'a>>,
+    fd_out: impl Into<SocketRef<'b>>,
+    len: usize,
+    flags: u32,
+) -> Result<Option<usize>, SysError> {
+    let (fd_in, fd_out) = (fd_in.into(), fd_out.into());
+    let bytes_copied = restartable(|| {
+        let bytes_copied = unsafe { syscall!(tee(fd_in.0, fd_out.0, len, flags)) };
+        if bytes_copied == -1 {
+            return Err(SysError::last_os_error("tee"));
+        }
+        Ok(bytes_copied as usize)
+    });
+    match bytes_copied {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_copied) => Ok(Some(bytes_copied)),
+    }
+}
@@ -0,0 +1,30 @@
+//! Process wide counters for the drivers to report how much work a loop took.
+//! Every wrapper goes through `syscall!`, so `syscalls` counts each trip into
+//! the kernel, retries after EINTR included. A wakeup is one return from
+//! `select`, `poll` or `epoll_wait`, whether something was ready or not.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static SYSCALLS: AtomicU64 = AtomicU64::new(0);
+static WAKEUPS: AtomicU64 = AtomicU64::new(0);
+
+pub fn syscalls() -> u64 {
+    SYSCALLS.load(Ordering::Relaxed)
+}
+
+pub fn wakeups() -> u64 {
+    WAKEUPS.load(Ordering::Relaxed)
+}
+
+/// Start counting from zero, e.g. at the top of a driver
+pub fn reset() {
+    SYSCALLS.store(0, Ordering::Relaxed);
+    WAKEUPS.store(0, Ordering::Relaxed);
+}
+
+pub(crate) fn count_syscall() {
+    SYSCALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn count_wakeup() {
+    WAKEUPS.fetch_add(1, Ordering::Relaxed);
+}
@@ -0,0 +1,93 @@
+//! Timerfd wrapper: a kernel timer that shows up as a readable fd when it
+//! expires, so timeouts can sit in the same epoll/poll/select set as the sockets.
+//! Closes the fd when dropped.
+use super::libc::{CLOCK_MONOTONIC, TFD_CLOEXEC, TFD_NONBLOCK, TFD_TIMER_ABSTIME, itimerspec};
+use super::{SysError, read, timerfd_create, timerfd_gettime, timerfd_settime};
+use crate::cyan;
+use std::time::Duration;
+
+/// When a timer fires: first after `value`, then every `interval`.
+/// A zero `value` means disarmed, a zero `interval` fires only once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimerSpec {
+    pub value: Duration,
+    pub interval: Duration,
+}
+
+impl From<TimerSpec> for itimerspec {
+    fn from(spec: TimerSpec) -> Self {
+        itimerspec {
+            it_interval: spec.interval.into(),
+            it_value: spec.value.into(),
+        }
+    }
+}
+
+impl From<itimerspec> for TimerSpec {
+    fn from(spec: itimerspec) -> Self {
+        TimerSpec {
+            value: spec.it_value.into(),
+            interval: spec.it_interval.into(),
+        }
+    }
+}
+
+pub struct TimerFd(pub(crate) i32);
+
+impl TimerFd {
+    /// On `CLOCK_MONOTONIC` and non-blocking, `read` never parks the event loop
+    pub fn new() -> Result<Self, SysError> {
+        let fd = timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK | TFD_CLOEXEC)?;
+        cyan!("Created timerfd {}", fd);
+        Ok(fd)
+    }
+
+    /// Fires once, `after` from now
+    pub fn set_oneshot(&self, after: Duration) -> Result<(), SysError> {
+        self.set(0, at_least_1ns(after), Duration::ZERO)
+    }
+
+    /// Fires every `interval`, the first time `interval` from now
+    pub fn set_interval(&self, interval: Duration) -> Result<(), SysError> {
+        let interval = at_least_1ns(interval);
+        self.set(0, interval, interval)
+    }
+
+    /// Fires once at `deadline` on `CLOCK_MONOTONIC` (see `clock_gettime`),
+    /// right away if that is already past. Unlike a relative timeout it
+    /// doesn't drift when re-armed late.
+    pub fn set_deadline(&self, deadline: Duration) -> Result<(), SysError> {
+        self.set(TFD_TIMER_ABSTIME, at_least_1ns(deadline), Duration::ZERO)
+    }
+
+    pub fn disarm(&self) -> Result<(), SysError> {
+        self.set(0, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// Time left and interval, zero when disarmed
+    pub fn get(&self) -> Result<TimerSpec, SysError> {
+        timerfd_gettime(self)
+    }
+
+    /// How many times it expired since the last read, 0 if it hasn't.
+    /// Reading clears the count and with it the readiness.
+    pub fn read(&self) -> Result<u64, SysError> {
+        let mut buf = [0u8; 8];
+        match read(self, &mut buf)? {
+            Some(_) => Ok(u64::from_ne_bytes(buf)),
+            None => Ok(0),
+        }
+    }
+
+    fn set(&self, flags: i32, value: Duration, interval: Duration) -> Result<(), SysError> {
+        timerfd_settime(self, flags, TimerSpec { value, interval })?;
+        Ok(())
+    }
+}
+
+/// A zero value would disarm the timer instead of firing it
+fn at_least_1ns(duration: Duration) -> Duration {
+    duration.max(Duration::from_nanos(1))
+}
+
+owned_fd_type!(TimerFd, "an open timerfd", "Timerfd closed");
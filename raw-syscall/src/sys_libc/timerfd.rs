@@ -0,0 +1,44 @@
+use super::libc::{itimerspec, timespec};
+use super::{SysError, TimerFd, TimerSpec};
+
+/// `flags` is any of `TFD_NONBLOCK` and `TFD_CLOEXEC`
+pub fn timerfd_create(clockid: i32, flags: i32) -> Result<TimerFd, SysError> {
+    let fd = unsafe { syscall!(timerfd_create(clockid, flags)) };
+    if fd == -1 {
+        return Err(SysError::last_os_error("timerfd_create"));
+    }
+    Ok(TimerFd(fd))
+}
+
+/// Arms (or disarms, with a zero `value`) the timer, returns the previous setting.
+/// With `TFD_TIMER_ABSTIME` in `flags` the value is a point on the timer's clock, see `clock_gettime`.
+pub fn timerfd_settime(fd: &TimerFd, flags: i32, spec: TimerSpec) -> Result<TimerSpec, SysError> {
+    let new_value = itimerspec::from(spec);
+    let mut old_value = empty();
+    let result = unsafe { syscall!(timerfd_settime(fd.0, flags, &new_value, &mut old_value)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("timerfd_settime"));
+    }
+    Ok(TimerSpec::from(old_value))
+}
+
+/// Time left until the next expiry (always relative) and the interval
+pub fn timerfd_gettime(fd: &TimerFd) -> Result<TimerSpec, SysError> {
+    let mut curr_value = empty();
+    let result = unsafe { syscall!(timerfd_gettime(fd.0, &mut curr_value)) };
+    if result == -1 {
+        return Err(SysError::last_os_error("timerfd_gettime"));
+    }
+    Ok(TimerSpec::from(curr_value))
+}
+
+fn empty() -> itimerspec {
+    let zero = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    itimerspec {
+        it_interval: zero,
+        it_value: zero,
+    }
+}
@@ -0,0 +1,19 @@
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+
+/// `write(2)` on any fd, sockets have `send`. Ok(None) when it would block.
+pub fn write<'a>(fd: impl Into<SocketRef<'a>>, buf: &[u8]) -> Result<Option<usize>, SysError> {
+    let fd = fd.into();
+    let bytes_written = restartable(|| {
+        let bytes_written = unsafe { syscall!(write(fd.0, buf.as_ptr(), buf.len())) };
+        if bytes_written == -1 {
+            return Err(SysError::last_os_error("write"));
+        }
+        Ok(bytes_written as usize)
+    });
+    match bytes_written {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_written) => Ok(Some(bytes_written)),
+    }
+}
@@ -0,0 +1,28 @@
+use super::libc::iovec;
+use super::retry::restartable;
+use super::{SocketRef, SysError};
+use std::io::IoSlice;
+
+/// `write` from several buffers in one call, in order, as if they were one.
+/// Returns the total written, which can stop anywhere in the middle of
+/// them (see `IoSlice::advance_slices`). Ok(None) when it would block.
+pub fn writev<'a>(
+    fd: impl Into<SocketRef<'a>>,
+    bufs: &[IoSlice],
+) -> Result<Option<usize>, SysError> {
+    let fd = fd.into();
+    // IoSlice is guaranteed to be an iovec on unix
+    let iov = bufs.as_ptr() as *const iovec;
+    let bytes_written = restartable(|| {
+        let bytes_written = unsafe { syscall!(writev(fd.0, iov, bufs.len() as i32)) };
+        if bytes_written == -1 {
+            return Err(SysError::last_os_error("writev"));
+        }
+        Ok(bytes_written as usize)
+    });
+    match bytes_written {
+        Err(err) if err.is_would_block() => Ok(None),
+        Err(err) => Err(err),
+        Ok(bytes_written) => Ok(Some(bytes_written)),
+    }
+}
@@ -0,0 +1,451 @@
+//! Talking to the kernel directly with `syscall`/`svc` instructions, no libc symbols.
+//!
+//! The functions mirror the signatures declared in `sys_libc/libc.rs`, so the
+//! `sys_libc` wrappers can switch to them with the `raw-syscalls` feature.
+//! The kernel returns `-errno` on failure; like libc we turn that into `-1`
+//! and keep the errno in a thread local, read back through `errno()`.
+//! Calls missing from the generic syscall table (`poll`, `select`, `epoll_wait`)
+//! are emulated with their newer siblings (`ppoll`, `pselect6`, `epoll_pwait`).
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+
+#[cfg(target_arch = "aarch64")]
+use aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use x86_64::*;
+
+use crate::sys_libc::libc::{
+    epoll_event, fd_set, io_uring_params, iovec, itimerspec, msghdr, nfds_t, pollfd, sigset_t,
+    sockaddr, timespec, timeval,
+};
+use core::ffi::c_void;
+use std::cell::Cell;
+use std::ptr;
+
+/// Size of the kernel's sigset_t (64 signals), not glibc's 1024 bit one
+const KERNEL_SIGSET_SIZE: usize = 8;
+
+thread_local! {
+    static ERRNO: Cell<i32> = const { Cell::new(0) };
+}
+
+/// errno of the last failed call made through this module on this thread
+pub fn errno() -> i32 {
+    ERRNO.get()
+}
+
+/// Pads missing arguments with zeros so every call goes through `syscall6`
+macro_rules! syscall {
+    ($nr:expr $(,)?) => {
+        syscall!($nr, 0, 0, 0, 0, 0, 0)
+    };
+    ($nr:expr, $a1:expr $(,)?) => {
+        syscall!($nr, $a1, 0, 0, 0, 0, 0)
+    };
+    ($nr:expr, $a1:expr, $a2:expr $(,)?) => {
+        syscall!($nr, $a1, $a2, 0, 0, 0, 0)
+    };
+    ($nr:expr, $a1:expr, $a2:expr, $a3:expr $(,)?) => {
+        syscall!($nr, $a1, $a2, $a3, 0, 0, 0)
+    };
+    ($nr:expr, $a1:expr, $a2:expr, $a3:expr, $a4:expr $(,)?) => {
+        syscall!($nr, $a1, $a2, $a3, $a4, 0, 0)
+    };
+    ($nr:expr, $a1:expr, $a2:expr, $a3:expr, $a4:expr, $a5:expr $(,)?) => {
+        syscall!($nr, $a1, $a2, $a3, $a4, $a5, 0)
+    };
+    ($nr:expr, $a1:expr, $a2:expr, $a3:expr, $a4:expr, $a5:expr, $a6:expr $(,)?) => {
+        check(unsafe {
+            syscall6(
+                $nr,
+                $a1 as usize,
+                $a2 as usize,
+                $a3 as usize,
+                $a4 as usize,
+                $a5 as usize,
+                $a6 as usize,
+            )
+        })
+    };
+}
+
+/// Values in -4095..=-1 are `-errno`, anything else is a successful result
+fn check(ret: isize) -> isize {
+    if (-4095..0).contains(&ret) {
+        ERRNO.set(-ret as i32);
+        -1
+    } else {
+        ret
+    }
+}
+
+fn millis_to_timespec(timeout: i32) -> timespec {
+    timespec {
+        tv_sec: (timeout / 1000) as i64,
+        tv_nsec: ((timeout % 1000) * 1_000_000) as i64,
+    }
+}
+
+pub unsafe fn socket(domain: i32, type_: i32, protocol: i32) -> i32 {
+    syscall!(SYS_SOCKET, domain, type_, protocol) as i32
+}
+
+pub unsafe fn connect(sockfd: i32, addr: *const sockaddr, addrlen: u32) -> i32 {
+    syscall!(SYS_CONNECT, sockfd, addr, addrlen) as i32
+}
+
+pub unsafe fn bind(sockfd: i32, addr: *const sockaddr, addrlen: u32) -> i32 {
+    syscall!(SYS_BIND, sockfd, addr, addrlen) as i32
+}
+
+pub unsafe fn listen(sockfd: i32, backlog: i32) -> i32 {
+    syscall!(SYS_LISTEN, sockfd, backlog) as i32
+}
+
+pub unsafe fn accept4(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32, flags: i32) -> i32 {
+    syscall!(SYS_ACCEPT4, sockfd, addr, addrlen, flags) as i32
+}
+
+pub unsafe fn getsockname(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32) -> i32 {
+    syscall!(SYS_GETSOCKNAME, sockfd, addr, addrlen) as i32
+}
+
+pub unsafe fn getpeername(sockfd: i32, addr: *mut sockaddr, addrlen: *mut u32) -> i32 {
+    syscall!(SYS_GETPEERNAME, sockfd, addr, addrlen) as i32
+}
+
+pub unsafe fn sendto(
+    sockfd: i32,
+    buf: *const u8,
+    len: usize,
+    flags: i32,
+    dest_addr: *const sockaddr,
+    addrlen: u32,
+) -> isize {
+    syscall!(SYS_SENDTO, sockfd, buf, len, flags, dest_addr, addrlen)
+}
+
+pub unsafe fn recvfrom(
+    sockfd: i32,
+    buf: *mut u8,
+    len: usize,
+    flags: i32,
+    src_addr: *mut sockaddr,
+    addrlen: *mut u32,
+) -> isize {
+    syscall!(SYS_RECVFROM, sockfd, buf, len, flags, src_addr, addrlen)
+}
+
+/// x86_64 and aarch64 have no `send` syscall, it is `sendto` without an address
+pub unsafe fn send(sockfd: i32, buf: *const u8, len: usize, flags: i32) -> isize {
+    unsafe { sendto(sockfd, buf, len, flags, ptr::null(), 0) }
+}
+
+/// x86_64 and aarch64 have no `recv` syscall, it is `recvfrom` without an address
+pub unsafe fn recv(sockfd: i32, buf: *mut u8, len: usize, flags: i32) -> isize {
+    unsafe { recvfrom(sockfd, buf, len, flags, ptr::null_mut(), ptr::null_mut()) }
+}
+
+pub unsafe fn socketpair(domain: i32, type_: i32, protocol: i32, sv: *mut [i32; 2]) -> i32 {
+    syscall!(SYS_SOCKETPAIR, domain, type_, protocol, sv) as i32
+}
+
+pub unsafe fn sendmsg(sockfd: i32, msg: *const msghdr, flags: i32) -> isize {
+    syscall!(SYS_SENDMSG, sockfd, msg, flags)
+}
+
+pub unsafe fn recvmsg(sockfd: i32, msg: *mut msghdr, flags: i32) -> isize {
+    syscall!(SYS_RECVMSG, sockfd, msg, flags)
+}
+
+pub unsafe fn close(fd: i32) -> i32 {
+    syscall!(SYS_CLOSE, fd) as i32
+}
+
+pub unsafe fn read(fd: i32, buf: *mut u8, count: usize) -> isize {
+    syscall!(SYS_READ, fd, buf, count)
+}
+
+pub unsafe fn write(fd: i32, buf: *const u8, count: usize) -> isize {
+    syscall!(SYS_WRITE, fd, buf, count)
+}
+
+pub unsafe fn readv(fd: i32, iov: *const iovec, iovcnt: i32) -> isize {
+    syscall!(SYS_READV, fd, iov, iovcnt)
+}
+
+pub unsafe fn writev(fd: i32, iov: *const iovec, iovcnt: i32) -> isize {
+    syscall!(SYS_WRITEV, fd, iov, iovcnt)
+}
+
+/// glibc's `pipe` is `pipe2` without flags, the old syscall is x86 only
+pub unsafe fn pipe2(fds: *mut [i32; 2], flags: i32) -> i32 {
+    syscall!(SYS_PIPE2, fds, flags) as i32
+}
+
+pub unsafe fn sendfile(out_fd: i32, in_fd: i32, offset: *mut i64, count: usize) -> isize {
+    syscall!(SYS_SENDFILE, out_fd, in_fd, offset, count)
+}
+
+pub unsafe fn splice(
+    fd_in: i32,
+    off_in: *mut i64,
+    fd_out: i32,
+    off_out: *mut i64,
+    len: usize,
+    flags: u32,
+) -> isize {
+    syscall!(SYS_SPLICE, fd_in, off_in, fd_out, off_out, len, flags)
+}
+
+pub unsafe fn tee(fd_in: i32, fd_out: i32, len: usize, flags: u32) -> isize {
+    syscall!(SYS_TEE, fd_in, fd_out, len, flags)
+}
+
+/// Errors come back as -1 like every other call here, which is also `MAP_FAILED`
+pub unsafe fn mmap(
+    addr: *mut c_void,
+    length: usize,
+    prot: i32,
+    flags: i32,
+    fd: i32,
+    offset: i64,
+) -> *mut c_void {
+    syscall!(SYS_MMAP, addr, length, prot, flags, fd, offset) as *mut c_void
+}
+
+pub unsafe fn munmap(addr: *mut c_void, length: usize) -> i32 {
+    syscall!(SYS_MUNMAP, addr, length) as i32
+}
+
+pub unsafe fn io_uring_setup(entries: u32, params: *mut io_uring_params) -> i32 {
+    syscall!(SYS_IO_URING_SETUP, entries, params) as i32
+}
+
+pub unsafe fn io_uring_enter(
+    fd: i32,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sig: *const sigset_t,
+) -> i32 {
+    syscall!(
+        SYS_IO_URING_ENTER,
+        fd,
+        to_submit,
+        min_complete,
+        flags,
+        sig,
+        KERNEL_SIGSET_SIZE
+    ) as i32
+}
+
+/// glibc's `eventfd` is `eventfd2`, the original syscall has no flags
+pub unsafe fn eventfd(initval: u32, flags: i32) -> i32 {
+    syscall!(SYS_EVENTFD2, initval, flags) as i32
+}
+
+/// Always a real syscall here, glibc answers most clocks from the vDSO without entering the kernel
+pub unsafe fn clock_gettime(clockid: i32, tp: *mut timespec) -> i32 {
+    syscall!(SYS_CLOCK_GETTIME, clockid, tp) as i32
+}
+
+/// The kernel's mask is 64 bits, so only that much of `oldset` is written
+pub unsafe fn sigprocmask(how: i32, set: *const sigset_t, oldset: *mut sigset_t) -> i32 {
+    syscall!(SYS_RT_SIGPROCMASK, how, set, oldset, KERNEL_SIGSET_SIZE) as i32
+}
+
+/// glibc's `signalfd` is `signalfd4`, the original syscall has no flags
+pub unsafe fn signalfd(fd: i32, mask: *const sigset_t, flags: i32) -> i32 {
+    syscall!(SYS_SIGNALFD4, fd, mask, KERNEL_SIGSET_SIZE, flags) as i32
+}
+
+pub unsafe fn timerfd_create(clockid: i32, flags: i32) -> i32 {
+    syscall!(SYS_TIMERFD_CREATE, clockid, flags) as i32
+}
+
+pub unsafe fn timerfd_settime(
+    fd: i32,
+    flags: i32,
+    new_value: *const itimerspec,
+    old_value: *mut itimerspec,
+) -> i32 {
+    syscall!(SYS_TIMERFD_SETTIME, fd, flags, new_value, old_value) as i32
+}
+
+pub unsafe fn timerfd_gettime(fd: i32, curr_value: *mut itimerspec) -> i32 {
+    syscall!(SYS_TIMERFD_GETTIME, fd, curr_value) as i32
+}
+
+pub unsafe fn getsockopt(
+    sockfd: i32,
+    level: i32,
+    optname: i32,
+    optval: *mut u8,
+    optlen: *mut u32,
+) -> i32 {
+    syscall!(SYS_GETSOCKOPT, sockfd, level, optname, optval, optlen) as i32
+}
+
+pub unsafe fn setsockopt(
+    sockfd: i32,
+    level: i32,
+    optname: i32,
+    optval: *const u8,
+    optlen: u32,
+) -> i32 {
+    syscall!(SYS_SETSOCKOPT, sockfd, level, optname, optval, optlen) as i32
+}
+
+/// `select` through `pselect6`: the timeval is converted to a timespec and
+/// the time left is written back, like the Linux `select` does.
+pub unsafe fn select(
+    nfds: i32,
+    readfds: *mut fd_set,
+    writefds: *mut fd_set,
+    exceptfds: *mut fd_set,
+    timeout: *mut timeval,
+) -> i32 {
+    let mut ts = unsafe { timeout.as_ref() }.map(|tv| timespec {
+        tv_sec: tv.tv_sec,
+        tv_nsec: tv.tv_usec * 1000,
+    });
+    let ts_ptr = match ts.as_mut() {
+        Some(ts) => ts as *mut timespec,
+        None => ptr::null_mut(),
+    };
+    // last argument is the optional signal mask, not needed for plain select
+    let result = syscall!(SYS_PSELECT6, nfds, readfds, writefds, exceptfds, ts_ptr, 0);
+    if let (Some(tv), Some(ts)) = (unsafe { timeout.as_mut() }, ts) {
+        tv.tv_sec = ts.tv_sec;
+        tv.tv_usec = ts.tv_nsec / 1000;
+    }
+    result as i32
+}
+
+/// `pselect6` takes the mask and its size through one pointer, since syscalls stop at 6 arguments.
+/// Like glibc's `pselect` the timeout is copied, so the caller's is not updated.
+pub unsafe fn pselect(
+    nfds: i32,
+    readfds: *mut fd_set,
+    writefds: *mut fd_set,
+    exceptfds: *mut fd_set,
+    timeout: *const timespec,
+    sigmask: *const sigset_t,
+) -> i32 {
+    let mut ts = unsafe { timeout.as_ref() }.copied();
+    let ts_ptr = match ts.as_mut() {
+        Some(ts) => ts as *mut timespec,
+        None => ptr::null_mut(),
+    };
+    let mask_and_size: [usize; 2] = [sigmask as usize, KERNEL_SIGSET_SIZE];
+    let mask_ptr = if sigmask.is_null() {
+        ptr::null()
+    } else {
+        &mask_and_size as *const [usize; 2]
+    };
+    syscall!(
+        SYS_PSELECT6,
+        nfds,
+        readfds,
+        writefds,
+        exceptfds,
+        ts_ptr,
+        mask_ptr
+    ) as i32
+}
+
+/// `poll` through `ppoll`, a negative timeout means wait forever
+pub unsafe fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: i32) -> i32 {
+    let ts = millis_to_timespec(timeout);
+    let ts_ptr = if timeout < 0 {
+        ptr::null()
+    } else {
+        &ts as *const timespec
+    };
+    syscall!(SYS_PPOLL, fds, nfds, ts_ptr, 0, KERNEL_SIGSET_SIZE) as i32
+}
+
+/// `epoll_create` only exists on x86_64, the size argument is ignored by the kernel anyway
+pub unsafe fn epoll_create(size: i32) -> i32 {
+    if size <= 0 {
+        ERRNO.set(crate::sys_libc::Errno::EINVAL.raw());
+        return -1;
+    }
+    unsafe { epoll_create1(0) }
+}
+
+pub unsafe fn epoll_create1(flags: i32) -> i32 {
+    syscall!(SYS_EPOLL_CREATE1, flags) as i32
+}
+
+pub unsafe fn epoll_ctl(epfd: i32, op: i32, fd: i32, event: *mut epoll_event) -> i32 {
+    syscall!(SYS_EPOLL_CTL, epfd, op, fd, event) as i32
+}
+
+/// `epoll_wait` through `epoll_pwait` without a signal mask
+pub unsafe fn epoll_wait(epfd: i32, events: *mut epoll_event, maxevents: i32, timeout: i32) -> i32 {
+    syscall!(
+        SYS_EPOLL_PWAIT,
+        epfd,
+        events,
+        maxevents,
+        timeout,
+        0,
+        KERNEL_SIGSET_SIZE
+    ) as i32
+}
+
+/// Unlike the kernel, glibc's `ppoll` leaves the caller's timeout alone, so copy it
+pub unsafe fn ppoll(
+    fds: *mut pollfd,
+    nfds: nfds_t,
+    timeout: *const timespec,
+    sigmask: *const sigset_t,
+) -> i32 {
+    let mut ts = unsafe { timeout.as_ref() }.copied();
+    let ts_ptr = match ts.as_mut() {
+        Some(ts) => ts as *mut timespec,
+        None => ptr::null_mut(),
+    };
+    syscall!(SYS_PPOLL, fds, nfds, ts_ptr, sigmask, KERNEL_SIGSET_SIZE) as i32
+}
+
+pub unsafe fn epoll_pwait(
+    epfd: i32,
+    events: *mut epoll_event,
+    maxevents: i32,
+    timeout: i32,
+    sigmask: *const sigset_t,
+) -> i32 {
+    syscall!(
+        SYS_EPOLL_PWAIT,
+        epfd,
+        events,
+        maxevents,
+        timeout,
+        sigmask,
+        KERNEL_SIGSET_SIZE
+    ) as i32
+}
+
+/// Same as `epoll_pwait` with a timespec timeout, ENOSYS before Linux 5.11
+pub unsafe fn epoll_pwait2(
+    epfd: i32,
+    events: *mut epoll_event,
+    maxevents: i32,
+    timeout: *const timespec,
+    sigmask: *const sigset_t,
+) -> i32 {
+    syscall!(
+        SYS_EPOLL_PWAIT2,
+        epfd,
+        events,
+        maxevents,
+        timeout,
+        sigmask,
+        KERNEL_SIGSET_SIZE
+    ) as i32
+}
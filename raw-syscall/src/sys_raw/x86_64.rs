@@ -0,0 +1,73 @@
+//! x86_64 syscall numbers and calling convention.
+//! Number in rax, arguments in rdi, rsi, rdx, r10, r8, r9, result in rax.
+//! The `syscall` instruction clobbers rcx and r11.
+use core::arch::asm;
+
+pub const SYS_READ: usize = 0;
+pub const SYS_WRITE: usize = 1;
+pub const SYS_CLOSE: usize = 3;
+pub const SYS_MMAP: usize = 9;
+pub const SYS_MUNMAP: usize = 11;
+pub const SYS_RT_SIGPROCMASK: usize = 14;
+pub const SYS_READV: usize = 19;
+pub const SYS_WRITEV: usize = 20;
+pub const SYS_SOCKET: usize = 41;
+pub const SYS_CONNECT: usize = 42;
+pub const SYS_SENDTO: usize = 44;
+pub const SYS_RECVFROM: usize = 45;
+pub const SYS_SENDMSG: usize = 46;
+pub const SYS_RECVMSG: usize = 47;
+pub const SYS_BIND: usize = 49;
+pub const SYS_LISTEN: usize = 50;
+pub const SYS_GETSOCKNAME: usize = 51;
+pub const SYS_GETPEERNAME: usize = 52;
+pub const SYS_SOCKETPAIR: usize = 53;
+pub const SYS_SETSOCKOPT: usize = 54;
+pub const SYS_GETSOCKOPT: usize = 55;
+pub const SYS_SENDFILE: usize = 40;
+pub const SYS_PSELECT6: usize = 270;
+pub const SYS_PPOLL: usize = 271;
+pub const SYS_SPLICE: usize = 275;
+pub const SYS_TEE: usize = 276;
+pub const SYS_CLOCK_GETTIME: usize = 228;
+pub const SYS_EPOLL_CTL: usize = 233;
+pub const SYS_EPOLL_PWAIT: usize = 281;
+pub const SYS_TIMERFD_CREATE: usize = 283;
+pub const SYS_TIMERFD_SETTIME: usize = 286;
+pub const SYS_TIMERFD_GETTIME: usize = 287;
+pub const SYS_ACCEPT4: usize = 288;
+pub const SYS_SIGNALFD4: usize = 289;
+pub const SYS_EVENTFD2: usize = 290;
+pub const SYS_EPOLL_CREATE1: usize = 291;
+pub const SYS_PIPE2: usize = 293;
+pub const SYS_IO_URING_SETUP: usize = 425;
+pub const SYS_IO_URING_ENTER: usize = 426;
+pub const SYS_EPOLL_PWAIT2: usize = 441;
+
+pub unsafe fn syscall6(
+    nr: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+    a5: usize,
+    a6: usize,
+) -> isize {
+    let ret: isize;
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") nr as isize => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            in("r10") a4,
+            in("r8") a5,
+            in("r9") a6,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
@@ -0,0 +1,186 @@
+//! The epoll driver with kernel timers instead of a timeout on `epoll_wait`.
+//!
+//! Every request gets its own timerfd armed at an absolute deadline, and a
+//! periodic timer ticks next to them. The wait itself never times out: a
+//! request that takes too long shows up as its timer becoming readable, and
+//! only that request is dropped. Every request gets `--timeout`, less than
+//! the delay server's 100ms (`--timeout=50ms`) makes the timers fire.
+use crate::non_blocking_epoll::send_request;
+use crate::report::Report;
+use crate::shutdown::{SIGNAL_TOKEN, Shutdown};
+use crate::sys_libc::libc::CLOCK_MONOTONIC;
+use crate::sys_libc::{
+    self, Epoll, Events, Interest, SockAddr, SocketFd, TcpNoDelay, TimerFd, stats,
+};
+use crate::workload::Workload;
+use crate::{cyan, green};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_millis(30);
+const TICK_TOKEN: u64 = u64::MAX - 1;
+
+/// What an event's token points at: sockets and their timers share the index
+enum Source {
+    Signal,
+    Tick,
+    Socket(usize),
+    Timeout(usize),
+}
+
+fn socket_token(idx: usize) -> u64 {
+    (idx as u64) << 1
+}
+
+fn timeout_token(idx: usize) -> u64 {
+    (idx as u64) << 1 | 1
+}
+
+fn source(token: u64) -> Source {
+    match token {
+        SIGNAL_TOKEN => Source::Signal,
+        TICK_TOKEN => Source::Tick,
+        token if token & 1 == 0 => Source::Socket((token >> 1) as usize),
+        token => Source::Timeout((token >> 1) as usize),
+    }
+}
+
+enum State {
+    /// Connecting, then sending: how much of the request is out
+    Sending(usize),
+    Sent,
+    Done,
+    TimedOut,
+}
+
+pub fn timer_calls(addr: SocketAddr, workload: &Workload) -> Result<Report, anyhow::Error> {
+    let start = Instant::now();
+    green!("--- timer_epoll ---");
+    stats::reset();
+    let server_addr = SockAddr::from(addr);
+    let epoll = Epoll::new()?;
+    let mut events = Events::with_capacity(workload.events_capacity);
+
+    let ticker = TimerFd::new()?;
+    ticker.set_interval(TICK)?;
+    epoll.add(&ticker, TICK_TOKEN, Interest::READABLE)?;
+    let shutdown = Shutdown::new()?;
+    epoll.add(&shutdown, SIGNAL_TOKEN, Interest::READABLE)?;
+    let request = workload.request();
+    let mut ticks = 0;
+
+    let mut received = Vec::with_capacity(workload.count);
+    let mut latencies = Vec::with_capacity(workload.count);
+    for round in workload.rounds() {
+        let mut sockets = vec![];
+        let mut timers = vec![];
+        // one deadline for the round, it doesn't move however late the loop runs
+        let deadline = sys_libc::clock_gettime(CLOCK_MONOTONIC)? + workload.timeout;
+        // the latencies count from here
+        let started = Instant::now();
+        for idx in 0..round.len() {
+            let socket = sys_libc::create_non_blocking_tcp_socket(server_addr.family())?;
+            sys_libc::set_opt(&socket, TcpNoDelay, true)?; // disable Nagle's algorithm
+            let in_and_out_edge_trigger = Interest::READABLE | Interest::WRITABLE | Interest::EDGE;
+            epoll.add(&socket, socket_token(idx), in_and_out_edge_trigger)?;
+            sys_libc::connect(&socket, &server_addr)?;
+
+            let timer = TimerFd::new()?;
+            timer.set_deadline(deadline)?;
+            epoll.add(&timer, timeout_token(idx), Interest::READABLE)?;
+            sockets.push(socket);
+            timers.push(timer);
+        }
+
+        let mut states: Vec<State> = sockets.iter().map(|_| State::Sending(0)).collect();
+        let mut responses = vec![Vec::new(); sockets.len()];
+        let mut round_latencies = vec![None; sockets.len()];
+
+        let pending = |states: &[State]| {
+            states
+                .iter()
+                .any(|s| matches!(s, State::Sending(_) | State::Sent))
+        };
+        'events: while pending(&states) {
+            // no timeout, the timers are the timeout
+            epoll.wait(&mut events, None)?;
+            for (token, readiness) in events.iter() {
+                match source(token) {
+                    Source::Signal => {
+                        if shutdown.check()?.is_some() {
+                            break 'events;
+                        }
+                    }
+                    Source::Tick => {
+                        // more than 1 if the loop was busy for longer than a tick
+                        ticks += ticker.read()?;
+                    }
+                    Source::Timeout(idx) => {
+                        if timers[idx].read()? == 0
+                            || !matches!(states[idx], State::Sending(_) | State::Sent)
+                        {
+                            continue;
+                        }
+                        cyan!(
+                            "Request {} timed out after {:?}, dropping it",
+                            round.start + idx,
+                            workload.timeout
+                        );
+                        states[idx] = State::TimedOut;
+                        epoll.delete(&sockets[idx])?;
+                        epoll.delete(&timers[idx])?;
+                    }
+                    Source::Socket(idx) => {
+                        let socket = &sockets[idx];
+                        if let State::Sending(sent) = states[idx]
+                            && readiness.is_writable()
+                        {
+                            let sent = send_request(socket, request.as_bytes(), sent)?;
+                            states[idx] = match sent == request.len() {
+                                true => State::Sent,
+                                false => State::Sending(sent),
+                            };
+                        }
+                        let readable = readiness.is_readable() || readiness.is_hup();
+                        if matches!(states[idx], State::Sent)
+                            && readable
+                            && read_until_would_block(socket, &mut responses[idx])?
+                        {
+                            // what the timer still had left, straight from the kernel
+                            let left = timers[idx].get()?.value;
+                            cyan!(
+                                "Request {} done with {:?} to spare",
+                                round.start + idx,
+                                left
+                            );
+                            states[idx] = State::Done;
+                            round_latencies[idx] = Some(started.elapsed());
+                            timers[idx].disarm()?;
+                            epoll.delete(socket)?;
+                            epoll.delete(&timers[idx])?;
+                        }
+                    }
+                }
+            }
+        }
+
+        received.extend(responses);
+        latencies.extend(round_latencies);
+        if shutdown.requested() {
+            break;
+        }
+    }
+    cyan!("{} ticks", ticks);
+    Ok(Report::new(received, latencies, start).stopped_by(&shutdown))
+}
+
+fn read_until_would_block(socket: &SocketFd, buffer: &mut Vec<u8>) -> Result<bool, anyhow::Error> {
+    let mut temp_buf = [0u8; 4096];
+    loop {
+        match sys_libc::recv(socket, &mut temp_buf)? {
+            None => return Ok(false), // would block, wait for the next edge
+            Some(0) => return Ok(true),
+            Some(n) => buffer.extend_from_slice(&temp_buf[..n]),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! What a client driver is asked to do: how many requests, how many at once,
+//! for which path and how long to wait. The CLI fills one in from the command
+//! line, library callers start from `Workload::default()` and change what they
+//! need, nothing here reads a file or the network.
+use crate::non_blocking_epoll::Mode;
+use crate::sys_libc::ResolvConf;
+use std::ops::Range;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Workload {
+    /// The `Host` header, the name the address was given as
+    pub host: String,
+    /// Requests in total
+    pub count: usize,
+    /// Connections open at once, the requests go in rounds of this many
+    pub concurrency: usize,
+    pub path: String,
+    /// How long a driver waits for the next event before giving up
+    pub timeout: Duration,
+    /// Events one `epoll_wait` can hand back, at least 1
+    pub events_capacity: usize,
+    /// How `non_blocking_epoll` registers its sockets
+    pub mode: Mode,
+    /// The nameservers `non_blocking_epoll` resolves a hostname with. The CLI
+    /// loads `/etc/resolv.conf`, `--nameserver` replaces the nameservers from it.
+    pub resolv_conf: ResolvConf,
+}
+
+impl Default for Workload {
+    /// Three requests for `/` at once, the nameserver on this host
+    fn default() -> Self {
+        Workload {
+            host: "localhost".to_string(),
+            count: 3,
+            concurrency: 3,
+            path: "/".to_string(),
+            timeout: Duration::from_secs(5),
+            events_capacity: 10,
+            mode: Mode::Edge,
+            resolv_conf: ResolvConf::parse(""),
+        }
+    }
+}
+
+impl Workload {
+    pub fn request(&self) -> String {
+        format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.path, self.host
+        )
+    }
+
+    /// The request indices, `concurrency` at a time. A round is over when all
+    /// its requests are, only then the next one connects.
+    pub fn rounds(&self) -> impl Iterator<Item = Range<usize>> {
+        // 0 would be no rounds at all, one at a time instead
+        let (count, concurrency) = (self.count, self.concurrency.max(1));
+        (0..count)
+            .step_by(concurrency)
+            .map(move |start| start..(start + concurrency).min(count))
+    }
+}
@@ -0,0 +1,213 @@
+//! Uploading a file to the delay server's `POST /upload` three ways, to see what
+//! the copies cost.
+//!
+//! - copy: `read` a chunk of the file into our buffer, `write` it to the socket.
+//!   Every byte crosses into user space and back.
+//! - sendfile: the kernel reads the file pages and queues them on the socket,
+//!   one call per chunk and no buffer of ours.
+//! - splice: file -> pipe -> socket, the pipe holds page references instead of
+//!   copies. Two calls per chunk, but it works for any pair of fds where one is
+//!   a pipe, sendfile needs the source to be a file. The response comes back the
+//!   same way, socket -> pipe, and `tee` duplicates it into a second pipe: one
+//!   copy is spliced into a file, the other is read to print it.
+use raw_syscall::sequential;
+use raw_syscall::sys_libc::libc::{O_CLOEXEC, SPLICE_F_MORE, SPLICE_F_MOVE};
+use raw_syscall::sys_libc::{self, PipeFd, SockAddr, SocketFd, stats};
+use raw_syscall::{cyan, green};
+use std::fs::File;
+use std::net::SocketAddr;
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const CHUNK: usize = 64 * 1024; // also the default pipe size
+const DEFAULT_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    Copy,
+    Sendfile,
+    Splice,
+}
+
+struct Run {
+    mode: Mode,
+    bytes: usize,
+    duration: Duration,
+    syscalls: u64,
+}
+
+impl Run {
+    fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1_000_000.0 / self.duration.as_secs_f64()
+    }
+}
+
+/// Uploads `file`, or a 16 MiB temp file when none is given
+pub fn zero_copy(addr: SocketAddr, file: Option<PathBuf>) -> Result<(), anyhow::Error> {
+    green!("--- zero_copy ---");
+    let (path, temp) = match file {
+        Some(path) => (path, false),
+        None => {
+            let path = std::env::temp_dir().join("raw-syscall-zero-copy.bin");
+            let data: Vec<u8> = (0..DEFAULT_SIZE).map(|i| (i % 251) as u8).collect();
+            std::fs::write(&path, data)?;
+            (path, true)
+        }
+    };
+    let results = [Mode::Copy, Mode::Sendfile, Mode::Splice]
+        .into_iter()
+        .map(|mode| upload(addr, &path, mode))
+        .collect::<Result<Vec<_>, _>>();
+    if temp {
+        std::fs::remove_file(&path)?;
+    }
+
+    for run in results? {
+        println!(
+            "{:>8}: {} bytes in {:?}, {:.0} MB/s, {} syscalls",
+            format!("{:?}", run.mode).to_lowercase(),
+            run.bytes,
+            run.duration,
+            run.mb_per_sec(),
+            run.syscalls
+        );
+    }
+    Ok(())
+}
+
+fn upload(addr: SocketAddr, path: &Path, mode: Mode) -> Result<Run, anyhow::Error> {
+    green!("{:?}", mode);
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    let server_addr = SockAddr::from(addr);
+    let socket = sys_libc::create_tcp_socket(server_addr.family())?;
+    sys_libc::connect(&socket, &server_addr)?;
+
+    // only the body is timed, the headers are the same for everyone
+    let headers = format!(
+        "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        len
+    );
+    send_all(&socket, headers.as_bytes())?;
+    stats::reset();
+    let start = Instant::now();
+    match mode {
+        Mode::Copy => copy_body(&socket, &file)?,
+        Mode::Sendfile => sendfile_body(&socket, &file, len)?,
+        Mode::Splice => splice_body(&socket, &file, len)?,
+    }
+    let duration = start.elapsed();
+    let syscalls = stats::syscalls();
+
+    let response = match mode {
+        Mode::Splice => splice_response(&socket)?,
+        _ => sequential::receive_all(&socket)?,
+    };
+    let response = String::from_utf8_lossy(&response);
+    println!("Response:\n{}", response);
+    if !response.ends_with(&format!("received {} bytes", len)) {
+        return Err(anyhow::anyhow!("{:?} upload didn't arrive whole", mode));
+    }
+    Ok(Run {
+        mode,
+        bytes: len,
+        duration,
+        syscalls,
+    })
+}
+
+fn send_all(socket: &SocketFd, mut buf: &[u8]) -> Result<(), anyhow::Error> {
+    while !buf.is_empty() {
+        // blocking socket, None doesn't happen
+        let n = sys_libc::send(socket, buf)?.unwrap_or(0);
+        buf = &buf[n..];
+    }
+    Ok(())
+}
+
+/// `write` is `send` without flags, minus a log line per chunk
+fn copy_body(socket: &SocketFd, file: &File) -> Result<(), anyhow::Error> {
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = sys_libc::read(file, &mut buf)?.unwrap_or(0);
+        if n == 0 {
+            return Ok(());
+        }
+        let mut chunk = &buf[..n];
+        while !chunk.is_empty() {
+            let written = sys_libc::write(socket, chunk)?.unwrap_or(0);
+            chunk = &chunk[written..];
+        }
+    }
+}
+
+fn sendfile_body(socket: &SocketFd, file: &File, len: usize) -> Result<(), anyhow::Error> {
+    let mut offset = 0i64;
+    while (offset as usize) < len {
+        // moves the offset by what it sent, the file position stays at 0
+        let left = len - offset as usize;
+        let sent = sys_libc::sendfile(socket, file, Some(&mut offset), left)?;
+        if sent == Some(0) {
+            return Err(anyhow::anyhow!("file shrank to {} bytes", offset));
+        }
+    }
+    Ok(())
+}
+
+fn splice_body(socket: &SocketFd, file: &File, len: usize) -> Result<(), anyhow::Error> {
+    let (pipe_out, pipe_in) = sys_libc::pipe2(O_CLOEXEC)?;
+    let mut offset = 0i64;
+    while (offset as usize) < len {
+        let flags = SPLICE_F_MOVE | SPLICE_F_MORE;
+        let in_pipe = sys_libc::splice(file, Some(&mut offset), &pipe_in, None, CHUNK, flags)?;
+        let in_pipe = match in_pipe {
+            Some(0) | None => return Err(anyhow::anyhow!("file shrank to {} bytes", offset)),
+            Some(n) => n,
+        };
+        drain_pipe(&pipe_out, socket, in_pipe)?;
+    }
+    Ok(())
+}
+
+/// Splices exactly `len` bytes out of the pipe, the socket can take less per call
+fn drain_pipe(pipe: &PipeFd, to: impl AsFd, mut len: usize) -> Result<(), anyhow::Error> {
+    while len > 0 {
+        let moved = sys_libc::splice(pipe, None, &to, None, len, SPLICE_F_MOVE)?.unwrap_or(0);
+        len -= moved;
+    }
+    Ok(())
+}
+
+/// socket -> pipe, `tee` into a second pipe, one copy to a file and one to us
+fn splice_response(socket: &SocketFd) -> Result<Vec<u8>, anyhow::Error> {
+    let (pipe_out, pipe_in) = sys_libc::pipe2(O_CLOEXEC)?;
+    let (tee_out, tee_in) = sys_libc::pipe2(O_CLOEXEC)?;
+    let path = std::env::temp_dir().join("raw-syscall-zero-copy-response.txt");
+    let saved = File::create(&path)?;
+    let mut response = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = sys_libc::splice(socket, None, &pipe_in, None, CHUNK, SPLICE_F_MOVE)?;
+        let n = match n {
+            Some(0) | None => break, // the server closed
+            Some(n) => n,
+        };
+        // the bytes stay in the first pipe for the splice into the file
+        let teed = sys_libc::tee(&pipe_out, &tee_in, n, 0)?.unwrap_or(0);
+        drain_pipe(&pipe_out, &saved, n)?;
+        let mut left = teed;
+        while left > 0 {
+            let read = sys_libc::read(&tee_out, &mut buf[..left.min(4096)])?.unwrap_or(0);
+            response.extend_from_slice(&buf[..read]);
+            left -= read;
+        }
+    }
+    cyan!(
+        "Response saved to {} ({} bytes), {} bytes read through tee",
+        path.display(),
+        saved.metadata()?.len(),
+        response.len()
+    );
+    Ok(response)
+}